@@ -1,22 +1,50 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    cursor,
+    event::{
+        self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event, KeyCode,
+        KeyEvent, KeyModifiers,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use rand::Rng;
+use log::{debug, info, LevelFilter, Log, Metadata, Record};
+use rand::{distributions::{Distribution, WeightedIndex}, rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style, Stylize},
+    layout::{Alignment, Constraint, Direction as Axis, Layout, Rect},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{BarChart, Block, BorderType, Borders, Clear, Paragraph},
     Frame, Terminal,
 };
-use std::{error::Error, io, thread, time::Duration};
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    env,
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{self, IsTerminal, Write},
+    panic,
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 // --- CONFIGURATION ---
-const TILE_WIDTH: u16 = 18; // Wide enough for 4 block digits
+const WIN_TILE: u32 = 2048; // Reaching this counts as a win (play carries on)
+const FLASH_MIN_TILE: u32 = 256; // Smallest merge result worth a --merge-flash
+const TILE_WIDTH: u16 = 18; // Wide enough for 4 block digits (so up to WIN_TILE)
 const TILE_HEIGHT: u16 = 7; // High enough for 5-row font + borders
+const MAX_TILE_SIZE: u16 = 200; // --tile-width/--tile-height cap; 4 of them still fit in a u16 many times over
+
+// Tile sizes tried by the auto-layout after Settings::tile_size, largest first. The short
+// ones can't hold the block font, so tiles fall back to plain text there.
+const TILE_SIZES: [(u16, u16); 4] = [(TILE_WIDTH, TILE_HEIGHT), (14, TILE_HEIGHT), (8, 3), (6, 3)];
 
 // --- BLOCK FONT (5 Rows x 3 Cols) ---
 // We use █ for a solid, readable look.
@@ -93,12 +121,200 @@ const FONT: [[&str; 5]; 10] = [
     ],
 ];
 
+// --- LETTER FONT (5 Rows x 3 Cols) ---
+// Used by --letters: 2 shows as A, 4 as B, 8 as C and so on.
+const LETTER_FONT: [[&str; 5]; 26] = [
+    [ // A
+        "███",
+        "█ █",
+        "███",
+        "█ █",
+        "█ █"
+    ],
+    [ // B
+        "██ ",
+        "█ █",
+        "██ ",
+        "█ █",
+        "██ "
+    ],
+    [ // C
+        "███",
+        "█  ",
+        "█  ",
+        "█  ",
+        "███"
+    ],
+    [ // D
+        "██ ",
+        "█ █",
+        "█ █",
+        "█ █",
+        "██ "
+    ],
+    [ // E
+        "███",
+        "█  ",
+        "███",
+        "█  ",
+        "███"
+    ],
+    [ // F
+        "███",
+        "█  ",
+        "███",
+        "█  ",
+        "█  "
+    ],
+    [ // G
+        "███",
+        "█  ",
+        "█ █",
+        "█ █",
+        "███"
+    ],
+    [ // H
+        "█ █",
+        "█ █",
+        "███",
+        "█ █",
+        "█ █"
+    ],
+    [ // I
+        "███",
+        " █ ",
+        " █ ",
+        " █ ",
+        "███"
+    ],
+    [ // J
+        "  █",
+        "  █",
+        "  █",
+        "█ █",
+        "███"
+    ],
+    [ // K
+        "█ █",
+        "█ █",
+        "██ ",
+        "█ █",
+        "█ █"
+    ],
+    [ // L
+        "█  ",
+        "█  ",
+        "█  ",
+        "█  ",
+        "███"
+    ],
+    [ // M
+        "█ █",
+        "███",
+        "███",
+        "█ █",
+        "█ █"
+    ],
+    [ // N
+        "██ ",
+        "█ █",
+        "█ █",
+        "█ █",
+        "█ █"
+    ],
+    [ // O
+        " █ ",
+        "█ █",
+        "█ █",
+        "█ █",
+        " █ "
+    ],
+    [ // P
+        "███",
+        "█ █",
+        "███",
+        "█  ",
+        "█  "
+    ],
+    [ // Q
+        "███",
+        "█ █",
+        "█ █",
+        "███",
+        "  █"
+    ],
+    [ // R
+        "██ ",
+        "█ █",
+        "██ ",
+        "█ █",
+        "█ █"
+    ],
+    [ // S
+        "███",
+        "█  ",
+        "███",
+        "  █",
+        "███"
+    ],
+    [ // T
+        "███",
+        " █ ",
+        " █ ",
+        " █ ",
+        " █ "
+    ],
+    [ // U
+        "█ █",
+        "█ █",
+        "█ █",
+        "█ █",
+        "███"
+    ],
+    [ // V
+        "█ █",
+        "█ █",
+        "█ █",
+        "█ █",
+        " █ "
+    ],
+    [ // W
+        "█ █",
+        "█ █",
+        "███",
+        "███",
+        "█ █"
+    ],
+    [ // X
+        "█ █",
+        "█ █",
+        " █ ",
+        "█ █",
+        "█ █"
+    ],
+    [ // Y
+        "█ █",
+        "█ █",
+        " █ ",
+        " █ ",
+        " █ "
+    ],
+    [ // Z
+        "███",
+        "  █",
+        " █ ",
+        "█  ",
+        "███"
+    ],
+];
+
 // --- GAME STRUCTURES ---
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 struct Tile {
     val: u32,
-    id: usize, // Helps tracking for future animations
+    id: usize,   // Helps tracking for future animations
+    frozen: u32, // Moves left before a frozen tile slides again (0 = free)
 }
 
 struct Game {
@@ -106,345 +322,5759 @@ struct Game {
     score: u32,
     game_over: bool,
     next_id: usize,
+    moves: u32,
+    started: Instant,
+    paused_at: Option<Instant>, // Set while --pause-on-blur has the clock stopped
+    last_merged: Vec<u32>,  // Tiles created by merges in the most recent move
+    rng: StdRng,
+    sandbox: Option<u32>,   // Final score, once the player is exploring past game over
+    history: Vec<Snapshot>, // Undo stack (sandbox only)
+    redo_stack: Vec<Snapshot>, // What undo took back; any new move forgets it
+    undos: u32,             // Times undo was used this game
+    ai_checked: u32,        // Moves compared against the solver
+    ai_matched: u32,        // ...and how many of them it agreed with
+    spawn: SpawnRules,
+    last_dir: Option<Direction>, // Most recent move that changed the board
+    dir_moves: [u32; 4],         // Moves that changed the board, per direction (Direction::index)
+    dir_merges: [u32; 4],        // ...and the merges they made
+    won: bool,              // Reached WIN_TILE at some point this game
+    best_tier: u32,         // Highest tile the board has held so far
+    milestones: Vec<u32>,   // Each new best_tier a move reached, oldest first
+    seed: u64,              // What the board was dealt from
+    anim_frames: Vec<Grid>, // Last move's animation, recorded by --debug-anim instead of played
+    last_spawn: Option<(usize, usize, u32)>, // Placed by animate_move, reported by run
+    freezes_left: u32,      // --powerups
+    reverted: Vec<(usize, usize)>, // Cells the last undo changed
+    move_log: Vec<Direction>, // Every move that changed the board, for the end-of-game replay
+    replayable: bool,         // False once something other than moves and the seed shaped the board
+    score_edited: bool,     // Debug commands changed the score or board, so validate can't check the score
+    spawn_override: Option<(usize, usize, u32)>, // Where and what the next spawn will be
 }
 
-impl Game {
+// Everything on screen that isn't the game itself: messages, highlights, the menu and cursor,
+// and keys that arrived mid-animation. draw_ui reads it next to the Game; none of it is
+// saved, replayed or checked.
+struct View {
+    notice: Option<Notice>, // Short-lived header message
+    banner: Option<Notice>, // Short-lived message over the middle of the board
+    flash: Option<u32>,     // Milestone tile whose colour is tinting the board right now
+    preview: Vec<usize>,    // Tile ids the merge preview is highlighting
+    pop_in: Option<usize>,  // Newly spawned tile id, drawn at half size for one frame
+    trail: Vec<(usize, usize)>, // Empty cells the last move's tiles passed through
+    menu: Option<usize>,    // Selected row while the settings menu is open
+    inspect: Option<(usize, usize)>, // Cursor while the tile inspector is open
+    show_header: bool,      // Off gives the board the header's rows too
+    queued_moves: VecDeque<Direction>, // Typed while an animation was playing
+    quit_requested: bool,   // Quit pressed mid-animation
+    warned_move: Option<Direction>, // --coach/--careful: the move that was just warned about, so pressing it again goes ahead
+    frame_stats: Cell<Option<FrameStats>>, // Timing of the last draw_ui, for --debug
+}
+
+#[derive(Clone, Copy)]
+struct FrameStats {
+    started: Instant,
+    draw: Duration,     // How long terminal.draw took
+    interval: Duration, // Since the draw before it; only means "fps" while animating
+}
+
+// How spawn_tile picks new tiles, and what the board starts with
+#[derive(Clone, Debug)]
+struct SpawnRules {
+    avoid_adjacent: bool, // Keep new tiles away from an equal neighbour when possible
+    weights: Vec<(u32, f64)>, // (value, probability) for each tile that can appear
+    ramp: bool,           // Spawns get bigger as the game goes on (see weights_at)
+    start_fill: Option<f64>, // --random-start: fraction of the board dealt at once instead of two tiles
+}
+
+const DEFAULT_SPAWN_WEIGHTS: [(u32, f64); 2] = [(2, 0.9), (4, 0.1)];
+
+impl Default for SpawnRules {
+    fn default() -> Self {
+        SpawnRules { avoid_adjacent: false, weights: DEFAULT_SPAWN_WEIGHTS.to_vec(), ramp: false, start_fill: None }
+    }
+}
+
+const RAMP_EVERY: u32 = 50; // Moves per difficulty level
+
+impl SpawnRules {
+    // The ramp goes up a level every RAMP_EVERY moves. Each level moves 3% of the smallest
+    // value's probability onto its double (capped at 30%); from level 6 on, another 1% per
+    // level (capped at 5%) goes to four times the smallest value.
+    fn weights_at(&self, moves: u32) -> Vec<(u32, f64)> {
+        let mut weights = self.weights.clone();
+        if !self.ramp {
+            return weights;
+        }
+        let Some(smallest) = weights.iter().map(|&(v, _)| v).min() else { return weights };
+        let level = f64::from(moves / RAMP_EVERY);
+        let double = (level * 0.03).min(0.3);
+        let quadruple = ((level - 5.0) * 0.01).clamp(0.0, 0.05);
+
+        for (value, extra) in [(smallest * 2, double), (smallest * 4, quadruple)] {
+            let Some(base) = weights.iter_mut().find(|(v, _)| *v == smallest) else { break };
+            let extra = extra.min(base.1);
+            base.1 -= extra;
+            match weights.iter_mut().find(|(v, _)| *v == value) {
+                Some(entry) => entry.1 += extra,
+                None => weights.push((value, extra)),
+            }
+        }
+        weights
+    }
+}
+
+// `[spawn]` maps tile values to probabilities, e.g. `2 = 0.8`, `4 = 0.18`, `8 = 0.02`
+fn parse_spawn_weights<'a, I: Iterator<Item = (&'a str, &'a str)>>(entries: I) -> Result<Option<Vec<(u32, f64)>>, String> {
+    let mut weights = Vec::new();
+    for (value, weight) in entries {
+        let value: u32 = value.parse().map_err(|_| format!("invalid spawn value '{}'", value))?;
+        if value < 2 || !value.is_power_of_two() {
+            return Err(format!("spawn value {} is not a power of two", value));
+        }
+        let weight: f64 = weight.parse().map_err(|_| format!("invalid spawn weight '{}'", weight))?;
+        if !(0.0..=1.0).contains(&weight) {
+            return Err(format!("spawn weight {} for {} is outside 0..1", weight, value));
+        }
+        weights.push((value, weight));
+    }
+    if weights.is_empty() {
+        return Ok(None);
+    }
+    let total: f64 = weights.iter().map(|&(_, w)| w).sum();
+    if (total - 1.0).abs() > 0.01 {
+        return Err(format!("spawn weights add up to {}, not 1", total));
+    }
+    Ok(Some(weights))
+}
+
+#[derive(Clone, Copy)]
+struct Snapshot {
+    grid: Grid,
+    score: u32,
+    moves: u32,
+}
+
+struct Notice {
+    text: String,
+    until: Instant,
+}
+
+impl View {
     fn new() -> Self {
+        View {
+            notice: None,
+            banner: None,
+            flash: None,
+            preview: Vec::new(),
+            pop_in: None,
+            trail: Vec::new(),
+            menu: None,
+            inspect: None,
+            show_header: true,
+            queued_moves: VecDeque::new(),
+            quit_requested: false,
+            warned_move: None,
+            frame_stats: Cell::new(None),
+        }
+    }
+
+    fn show_notice(&mut self, text: &str, duration: Duration) {
+        self.notice = Some(Notice { text: text.to_string(), until: Instant::now() + duration });
+    }
+
+    // Drops the header notice and the board banner once their time is up
+    fn expire_notice(&mut self) -> bool {
+        let now = Instant::now();
+        let mut expired = false;
+        for slot in [&mut self.notice, &mut self.banner] {
+            if slot.as_ref().is_some_and(|n| now >= n.until) {
+                *slot = None;
+                expired = true;
+            }
+        }
+        expired
+    }
+}
+
+impl Game {
+    // Without a seed one is picked here and kept, so any board can be shown and replayed
+    fn new(seed: Option<u64>, spawn: SpawnRules) -> Self {
+        let seed = seed.unwrap_or_else(rand::random);
+        let rng = StdRng::seed_from_u64(seed);
         let mut game = Game {
             grid: [[None; 4]; 4],
             score: 0,
             game_over: false,
             next_id: 0,
+            moves: 0,
+            started: Instant::now(),
+            paused_at: None,
+            last_merged: Vec::new(),
+            won: false,
+            best_tier: 0,
+            milestones: Vec::new(),
+            seed,
+            anim_frames: Vec::new(),
+            last_spawn: None,
+            freezes_left: FREEZE_CHARGES,
+            reverted: Vec::new(),
+            move_log: Vec::new(),
+            replayable: true,
+            score_edited: false,
+            spawn_override: None,
+            rng,
+            sandbox: None,
+            history: Vec::new(),
+            redo_stack: Vec::new(),
+            undos: 0,
+            ai_checked: 0,
+            ai_matched: 0,
+            spawn,
+            last_dir: None,
+            dir_moves: [0; 4],
+            dir_merges: [0; 4],
         };
-        game.spawn_tile();
-        game.spawn_tile();
+        match game.spawn.start_fill {
+            Some(fill) => game.deal_random_start(fill),
+            None => {
+                game.spawn_tile();
+                game.spawn_tile();
+            }
+        }
+        game.best_tier = game.max_tile();
         game
     }
 
-    fn spawn_tile(&mut self) {
-        let mut empty = Vec::new();
-        for r in 0..4 {
-            for c in 0..4 {
-                if self.grid[r][c].is_none() {
-                    empty.push((r, c));
-                }
+    // Unfreezes a finished board so it can be explored; the real score is kept aside
+    fn enter_sandbox(&mut self) {
+        self.sandbox = Some(self.score);
+        self.game_over = false;
+    }
+
+    // Swaps the starting tiles for an imported board; the seed still drives what spawns next
+    fn load_board(&mut self, board: &ImportedBoard) {
+        for (r, row) in board.values.iter().enumerate() {
+            for (c, &val) in row.iter().enumerate() {
+                self.grid[r][c] = (val != 0).then(|| Tile { val, id: self.next_id + r * 4 + c, frozen: 0 });
             }
         }
-        if empty.is_empty() { return; }
-        
-        let idx = rand::thread_rng().gen_range(0..empty.len());
-        let (r, c) = empty[idx];
-        let val = if rand::thread_rng().gen_bool(0.9) { 2 } else { 4 };
-        
-        self.grid[r][c] = Some(Tile { val, id: self.next_id });
-        self.next_id += 1;
+        self.next_id += 16;
+        self.score = board.score.unwrap_or(0);
+        self.best_tier = self.max_tile();
+        self.score_edited = true; // Nobody knows how the board was built
+        self.replayable = false;
     }
-}
-
-// --- RENDERING HELPERS ---
 
-fn get_color_style(val: u32) -> Style {
-    // Distinct colors for each tier
-    let (fg, bg) = match val {
-        2 => (Color::Black, Color::White),        // White
-        4 => (Color::Black, Color::Yellow),       // Yellow
-        8 => (Color::White, Color::LightRed),     // Orange-ish
-        16 => (Color::White, Color::Red),         // Red
-        32 => (Color::White, Color::Magenta),     // Pink
-        64 => (Color::White, Color::Blue),        // Blue
-        128 => (Color::White, Color::Cyan),       // Cyan
-        256 => (Color::Black, Color::LightCyan),  // Light Cyan
-        512 => (Color::Black, Color::Green),      // Green
-        1024 => (Color::White, Color::DarkGray),  // Grey
-        2048 => (Color::Yellow, Color::Black),    // Black/Gold
-        _ => (Color::Red, Color::Black),          // Super high
-    };
-    Style::default().fg(fg).bg(bg).add_modifier(Modifier::BOLD)
-}
+    fn snapshot(&self) -> Snapshot {
+        Snapshot { grid: self.grid, score: self.score, moves: self.moves }
+    }
 
-fn render_block_text(val: u32) -> Vec<Line<'static>> {
-    let s = val.to_string();
-    let digits: Vec<usize> = s.chars().map(|c| c.to_digit(10).unwrap() as usize).collect();
+    fn push_history(&mut self) {
+        self.history.push(self.snapshot());
+    }
 
-    let mut lines = vec![String::new(); 5];
+    fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(snap) => {
+                self.redo_stack.push(self.snapshot());
+                self.restore(snap);
+                self.undos += 1;
+                true
+            }
+            None => false,
+        }
+    }
 
-    // Construct the 5 lines of text by stitching digits together
-    for &d in digits.iter() {
-        for row in 0..5 {
-            lines[row].push_str(FONT[d][row]);
-            lines[row].push(' '); // Spacer between digits
+    fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(snap) => {
+                self.push_history();
+                self.restore(snap);
+                true
+            }
+            None => false,
         }
     }
 
-    lines.into_iter().map(Line::from).collect()
-}
+    // Undo and redo both jump to a snapshot, noting the cells that change for --undo-highlight
+    fn restore(&mut self, snap: Snapshot) {
+        let value = |grid: &Grid, r: usize, c: usize| grid[r][c].map(|t| t.val);
+        self.reverted = (0..16)
+            .map(|i| (i / 4, i % 4))
+            .filter(|&(r, c)| value(&self.grid, r, c) != value(&snap.grid, r, c))
+            .collect();
+        self.grid = snap.grid;
+        self.score = snap.score;
+        self.moves = snap.moves;
+    }
 
-// --- ANIMATION ENGINE ---
+    // Play time so far, not counting pauses
+    fn elapsed(&self) -> Duration {
+        self.paused_at.unwrap_or_else(Instant::now).saturating_duration_since(self.started)
+    }
 
-// Moves grid visually step-by-step
-fn animate_move<B: ratatui::backend::Backend>(
-    terminal: &mut Terminal<B>, 
-    game: &mut Game, 
-    dr: i32, 
-    dc: i32
-) -> io::Result<bool> {
-    let mut something_moved = false;
-    let steps = 4; // Check up to 4 slots away
+    fn pause(&mut self) {
+        self.paused_at.get_or_insert_with(Instant::now);
+    }
 
-    // 1. VISUAL SLIDE
-    for _ in 0..steps {
-        let mut step_moved = false;
-        let mut next_grid = game.grid;
-        
-        // Iteration order matters to prevent overwriting
-        let r_iter: Vec<usize> = if dr > 0 { (0..4).rev().collect() } else { (0..4).collect() };
-        let c_iter: Vec<usize> = if dc > 0 { (0..4).rev().collect() } else { (0..4).collect() };
+    // Pushes the start forward by the length of the pause, so the clock picks up where it stopped
+    fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.started += paused_at.elapsed();
+        }
+    }
 
-        for &r in &r_iter {
-            for &c in &c_iter {
-                if let Some(tile) = game.grid[r][c] {
-                    let nr = r as i32 + dr;
-                    let nc = c as i32 + dc;
-
-                    if nr >= 0 && nr < 4 && nc >= 0 && nc < 4 {
-                        let nr = nr as usize;
-                        let nc = nc as usize;
-                        if game.grid[nr][nc].is_none() {
-                            next_grid[nr][nc] = Some(tile);
-                            next_grid[r][c] = None;
-                            step_moved = true;
-                            something_moved = true;
-                        }
-                    }
-                }
-            }
+    // Returns where the new tile went, if there was room for one
+    fn spawn_tile(&mut self) -> Option<(usize, usize, u32)> {
+        // A --force-spawn lasts one spawn, and only if its cell is free
+        if let Some((r, c, val)) = self.spawn_override.take()
+            && self.grid[r][c].is_none()
+        {
+            self.grid[r][c] = Some(Tile { val, id: self.next_id, frozen: 0 });
+            self.next_id += 1;
+            self.score_edited = true; // A big free tile throws off validate's score check
+            self.replayable = false;
+            return Some((r, c, val));
         }
 
-        if step_moved {
-            game.grid = next_grid;
-            draw_ui(terminal, game)?;
-            thread::sleep(Duration::from_millis(50)); // Animation speed
+        let empty = empty_cells(&self.grid);
+        if empty.is_empty() { return None; }
+        
+        let (r, c, val) = if self.spawn.avoid_adjacent {
+            // Value first, so we know which neighbours would give a free merge
+            let val = self.roll_value();
+            let safe: Vec<(usize, usize)> =
+                empty.iter().copied().filter(|&(r, c)| !touches_value(&self.grid, r, c, val)).collect();
+            let pool = if safe.is_empty() { &empty } else { &safe };
+            let (r, c) = pool[self.rng.gen_range(0..pool.len())];
+            (r, c, val)
         } else {
-            break; 
-        }
+            let idx = self.rng.gen_range(0..empty.len());
+            let (r, c) = empty[idx];
+            (r, c, self.roll_value())
+        };
+        
+        self.grid[r][c] = Some(Tile { val, id: self.next_id, frozen: 0 });
+        self.next_id += 1;
+        Some((r, c, val))
     }
 
-    // 2. MERGE LOGIC
-    let mut merged = false;
-    let mut next_grid = game.grid;
-    let mut merged_mask = [[false; 4]; 4]; // Prevent double merges
-    
-    let r_iter: Vec<usize> = if dr > 0 { (0..4).rev().collect() } else { (0..4).collect() };
-    let c_iter: Vec<usize> = if dc > 0 { (0..4).rev().collect() } else { (0..4).collect() };
-
-    for &r in &r_iter {
-        for &c in &c_iter {
-            if let Some(tile) = game.grid[r][c] {
-                let nr = r as i32 + dr;
-                let nc = c as i32 + dc;
-                if nr >= 0 && nr < 4 && nc >= 0 && nc < 4 {
-                    let nr = nr as usize;
-                    let nc = nc as usize;
-                    
-                    if let Some(target) = next_grid[nr][nc] {
-                        if target.val == tile.val && !merged_mask[nr][nc] && !merged_mask[r][c] {
-                            // Merge happens
-                            next_grid[nr][nc] = Some(Tile { val: tile.val * 2, id: tile.id });
-                            next_grid[r][c] = None;
-                            game.score += tile.val * 2;
-                            merged_mask[nr][nc] = true;
-                            merged = true;
-                            something_moved = true;
-                        }
-                    }
-                }
-            }
+    // Scatters the smallest spawnable values over `fill` of the board. At most 15 tiles: a full
+    // board is game over on the spot, and any free cell leaves at least one move that slides.
+    fn deal_random_start(&mut self, fill: f64) {
+        let count = ((16.0 * fill).round() as usize).clamp(2, 15);
+        let mut values: Vec<u32> = self.spawn.weights.iter().map(|&(v, _)| v).collect();
+        values.sort_unstable();
+        values.truncate(2);
+        let cells: Vec<(usize, usize)> = empty_cells(&self.grid).choose_multiple(&mut self.rng, count).copied().collect();
+        for (r, c) in cells {
+            let val = *values.choose(&mut self.rng).unwrap_or(&2);
+            self.grid[r][c] = Some(Tile { val, id: self.next_id, frozen: 0 });
+            self.next_id += 1;
         }
     }
 
-    if merged {
-        game.grid = next_grid;
-        draw_ui(terminal, game)?;
-        thread::sleep(Duration::from_millis(50));
-        
-        // Snap slide after merge (cleanup gaps)
-        for _ in 0..4 {
-             let mut snap_grid = game.grid;
-             let mut snapped = false;
-             for &r in &r_iter {
-                for &c in &c_iter {
-                    if let Some(tile) = snap_grid[r][c] {
-                        let nr = r as i32 + dr;
-                        let nc = c as i32 + dc;
-                        if nr >= 0 && nr < 4 && nc >= 0 && nc < 4 {
-                            let nr = nr as usize;
-                            let nc = nc as usize;
-                            if snap_grid[nr][nc].is_none() {
-                                snap_grid[nr][nc] = Some(tile);
-                                snap_grid[r][c] = None;
-                                snapped = true;
-                            }
-                        }
-                    }
-                }
-             }
-             if snapped { game.grid = snap_grid; } else { break; }
+    // An extra 2 on top of the normal spawn, for --garbage
+    fn spawn_garbage(&mut self) -> Option<(usize, usize, u32)> {
+        let &(r, c) = empty_cells(&self.grid).choose(&mut self.rng)?;
+        self.grid[r][c] = Some(Tile { val: 2, id: self.next_id, frozen: 0 });
+        self.next_id += 1;
+        Some((r, c, 2))
+    }
+
+    fn roll_value(&mut self) -> u32 {
+        let weights = self.spawn.weights_at(self.moves);
+        match WeightedIndex::new(weights.iter().map(|&(_, w)| w)) {
+            Ok(dist) => weights[dist.sample(&mut self.rng)].0,
+            Err(_) => 2, // Unreachable for weights that passed parse_spawn_weights
         }
-        draw_ui(terminal, game)?;
     }
 
-    Ok(something_moved)
-}
+    // Occupied cells as (row, col, tile), row-major
+    fn tiles(&self) -> impl Iterator<Item = (usize, usize, Tile)> + '_ {
+        self.grid
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| row.iter().enumerate().filter_map(move |(c, cell)| cell.map(|tile| (r, c, tile))))
+    }
 
-// --- DRAWING ---
+    fn max_tile(&self) -> u32 {
+        self.tiles().map(|(_, _, tile)| tile.val).max().unwrap_or(0)
+    }
 
-fn draw_ui<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, game: &Game) -> io::Result<()> {
-    terminal.draw(|f| {
-        let size = f.size();
-        
-        // Vertical Split
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
-            .split(size);
+    // Fires once per tier: the first time the board's biggest tile beats best_tier
+    fn note_milestone(&mut self) -> Option<u32> {
+        let max = self.max_tile();
+        if max <= self.best_tier {
+            return None;
+        }
+        self.best_tier = max;
+        self.milestones.push(max);
+        Some(max)
+    }
 
-        // Header
-        let title = Paragraph::new(format!(" SCORE: {} ", game.score))
-            .style(Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD))
-            .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
-        f.render_widget(title, chunks[0]);
+    fn tile_count(&self) -> usize {
+        self.tiles().count()
+    }
 
-        // Centering Logic
-        let board_w = 4 * TILE_WIDTH;
-        let board_h = 4 * TILE_HEIGHT;
-
-        let center_y = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length((size.height.saturating_sub(board_h)) / 2),
-                Constraint::Length(board_h),
-                Constraint::Min(0),
-            ].as_ref())
-            .split(chunks[1]);
-
-        let center_x = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Length((size.width.saturating_sub(board_w)) / 2),
-                Constraint::Length(board_w),
-                Constraint::Min(0),
-            ].as_ref())
-            .split(center_y[1]);
-
-        let board_area = center_x[1];
+    // Board invariants, checked after every move in debug builds
+    fn validate(&self, rule: ScoreRule, merge_rule: MergeRule) -> Result<(), String> {
+        let tiles: Vec<Tile> = self.tiles().map(|(_, _, tile)| tile).collect();
+        let mut ids: Vec<usize> = tiles.iter().map(|t| t.id).collect();
+        ids.sort_unstable();
+        if let Some(pair) = ids.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(format!("tile id {} appears twice", pair[0]));
+        }
 
-        // Draw Background Board
-        let board_block = Block::default().borders(Borders::ALL).title(" RUST 2048 ");
-        f.render_widget(board_block, board_area);
+        // The value checks only know how doubling boards add up
+        if !matches!(merge_rule, MergeRule::Doubling) {
+            return Ok(());
+        }
+        if let Some(tile) = tiles.iter().find(|t| t.val < 2 || !t.val.is_power_of_two()) {
+            return Err(format!("tile {} has value {}", tile.id, tile.val));
+        }
 
-        // Draw Tiles
-        for r in 0..4 {
-            for c in 0..4 {
-                let cell_area = Rect {
-                    x: board_area.x + (c as u16 * TILE_WIDTH),
-                    y: board_area.y + (r as u16 * TILE_HEIGHT),
-                    width: TILE_WIDTH,
-                    height: TILE_HEIGHT,
-                };
+        // Under the standard rule a tile is worth at least what it scores when built from the
+        // biggest spawn value that fits in it
+        let spawns = self.spawn.weights_at(u32::MAX);
+        let earned: u32 = tiles
+            .iter()
+            .map(|t| {
+                let spawn = spawns.iter().map(|&(v, _)| v).filter(|&v| v <= t.val).max();
+                t.val * (t.val.ilog2() - spawn.unwrap_or(t.val).ilog2())
+            })
+            .sum();
+        if rule == ScoreRule::Standard && !self.score_edited && self.score < earned {
+            return Err(format!("score {} is below the {} the board's tiles account for", self.score, earned));
+        }
+        Ok(())
+    }
 
-                // Add padding inside the cell so borders don't touch text
-                let inner_area = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)])
-                    .split(cell_area)[1];
-                
-                let inner_area = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)])
-                    .split(inner_area)[1];
+    // The cell values in row-major order (ids don't count), so it's the same every run
+    fn board_hash(&self) -> u64 {
+        fnv1a(self.grid.iter().flatten().flat_map(|cell| cell.map_or(0, |t| t.val).to_le_bytes()))
+    }
 
-                if let Some(tile) = game.grid[r][c] {
-                    let style = get_color_style(tile.val);
-                    let text_lines = render_block_text(tile.val);
-                    
-                    let p = Paragraph::new(text_lines)
-                        .alignment(Alignment::Center)
-                        .block(Block::default().borders(Borders::ALL))
-                        .style(style);
-                    f.render_widget(p, cell_area);
-                } else {
-                    let p = Paragraph::new("")
-                        .block(Block::default().borders(Borders::ALL).style(Style::default().fg(Color::DarkGray)));
-                    f.render_widget(p, cell_area);
-                }
-            }
+    // Shuffles every tile to a random cell; values, ids and score are untouched
+    fn scramble(&mut self) {
+        self.replayable = false;
+        let tiles: Vec<Tile> = self.tiles().map(|(_, _, tile)| tile).collect();
+
+        let mut cells: Vec<(usize, usize)> = (0..4).flat_map(|r| (0..4).map(move |c| (r, c))).collect();
+        cells.shuffle(&mut self.rng);
+
+        self.grid = [[None; 4]; 4];
+        for (tile, (r, c)) in tiles.into_iter().zip(cells) {
+            self.grid[r][c] = Some(tile);
         }
+    }
+}
 
-        if game.game_over {
-            let p = Paragraph::new(" GAME OVER - Press 'q' ")
-                .style(Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD))
-                .alignment(Alignment::Center);
-            
-            let mid_rect = Rect {
-                x: board_area.x + board_w/2 - 12,
-                y: board_area.y + board_h/2,
-                width: 24,
-                height: 1
-            };
-            f.render_widget(p, mid_rect);
+// --- SETTINGS ---
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum HeaderField {
+    Score,
+    Moves,
+    Time,
+    LastMove,
+    Challenge,
+    Progress,
+    Seed,
+}
+
+impl HeaderField {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "score" => Ok(HeaderField::Score),
+            "moves" => Ok(HeaderField::Moves),
+            "time" => Ok(HeaderField::Time),
+            "last" => Ok(HeaderField::LastMove),
+            "code" => Ok(HeaderField::Challenge),
+            "progress" => Ok(HeaderField::Progress),
+            "seed" => Ok(HeaderField::Seed),
+            other => Err(format!(
+                "unknown header field '{}' (expected score, moves, time, last, code, progress or seed)",
+                other
+            )),
         }
+    }
+}
 
-    })?;
-    Ok(())
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Theme {
+    Classic,
+    Original { depth: ColorDepth }, // The web game's beige and orange, as near as the terminal gets
+    Mono,                           // No colours at all, just bold/reverse/underline
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+impl Theme {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "classic" => Ok(Theme::Classic),
+            "original" => Ok(Theme::Original { depth: ColorDepth::detect() }),
+            "mono" => Ok(Theme::Mono),
+            other => Err(format!("unknown theme '{}' (expected classic, original or mono)", other)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => "classic",
+            Theme::Original { .. } => "original",
+            Theme::Mono => "mono",
+        }
+    }
+}
 
-    let mut game = Game::new();
-    draw_ui(&mut terminal, &game)?;
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
 
-    loop {
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Char('q') {
-                    break;
-                }
-                
-                if !game.game_over {
-                    let moved = match key.code {
-                        KeyCode::Up | KeyCode::Char('w') => animate_move(&mut terminal, &mut game, -1, 0)?,
-                        KeyCode::Down | KeyCode::Char('s') => animate_move(&mut terminal, &mut game, 1, 0)?,
-                        KeyCode::Left | KeyCode::Char('a') => animate_move(&mut terminal, &mut game, 0, -1)?,
-                        KeyCode::Right | KeyCode::Char('d') => animate_move(&mut terminal, &mut game, 0, 1)?,
-                        _ => false,
-                    };
+impl ColorDepth {
+    // 24-bit terminals say so in COLORTERM, 256-colour ones in TERM; anything else gets the basics
+    fn detect() -> Self {
+        if env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit") {
+            ColorDepth::TrueColor
+        } else if env::var("TERM").is_ok_and(|v| v.contains("256color")) {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "truecolor" => Ok(ColorDepth::TrueColor),
+            "256" => Ok(ColorDepth::Ansi256),
+            "16" => Ok(ColorDepth::Ansi16),
+            other => Err(format!("unknown colour depth '{}' (expected truecolor, 256 or 16)", other)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ColorDepth::TrueColor => "truecolor",
+            ColorDepth::Ansi256 => "256",
+            ColorDepth::Ansi16 => "16",
+        }
+    }
+}
+
+// NO_COLOR (https://no-color.org) or --no-color beats --theme, --force-color beats both.
+// The original theme gets `depth`, see Settings::color_depth.
+fn resolve_theme(requested: Theme, no_color: bool, force_color: bool, depth: ColorDepth) -> Theme {
+    match requested {
+        _ if no_color && !force_color => Theme::Mono,
+        Theme::Original { .. } => Theme::Original { depth },
+        other => other,
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Settings {
+    letters: bool,              // Show tiles as A, B, C... instead of 2, 4, 8...
+    exponent: bool,             // Show tiles as 1, 2, 3... (log2 of the value)
+    abbrev: bool,               // Show big tiles as 16K, 1M...
+    header: Vec<HeaderField>,   // Header elements, left to right
+    minimal_header: bool,       // Show only the title in the header
+    combo_notices: bool,        // Flash "Double merge!" etc. for multi-merge moves
+    seed: Option<u64>,          // Fixed RNG seed for reproducible games
+    scramble: bool,             // Enable the 'x' scramble command
+    merge_preview: bool,        // Shift + direction highlights the pairs that would merge
+    minimap: bool,              // One-character-per-cell overview in the corner
+    sandbox: bool,              // 'p' on game over keeps playing (with undo) without counting
+    track_optimal: bool,        // Compare every move with the solver, report on game over
+    theme: Theme,               // What gets drawn, after resolve_theme
+    requested_theme: Theme,     // What the flags, config or menu asked for; saved instead of theme
+    force_color: bool,          // Ignore NO_COLOR
+    no_color: bool,             // Same as NO_COLOR
+    color_depth: Option<ColorDepth>, // Pinned by --color-depth or the config; None = detect
+    cursor_color: Option<Color>, // Inspector and freeze cursor, from --cursor-color or the config
+    legend: bool,               // Sidebar listing every tile colour
+    score_rule: ScoreRule,
+    merge_rule: MergeRule,
+    no_adjacent_spawn: bool,    // Harder: new tiles avoid landing next to their twin
+    splash: bool,               // Title screen before the game starts
+    fancy_tiles: bool,          // Half-block edges instead of box-drawing borders
+    debug_anim: bool,           // Record each move's frames and step through them by hand
+    instant_settle: bool,       // Close post-merge gaps without drawing them first
+    pause_on_blur: bool,        // Stop the clock while the terminal doesn't have focus
+    save_settings: bool,        // Write menu changes back to the config file on exit
+    trails: bool,               // Dim the cells tiles slid through, for one frame
+    undo_highlight: bool,       // Outline the cells an undo put back, for one frame
+    shatter: bool,              // Clear the board cell by cell when the game ends
+    // Overrides the rest of the animation settings: no slide, merge or pop-in frames (so no
+    // trails either), no merge flash, no undo highlight, no shatter, no win celebration, no
+    // spawn delay. Moves land in one redraw.
+    reduce_motion: bool,
+    spawn_anim: bool,           // The pop-in frame after every move; speed-runners turn it off
+    celebrate: Duration,        // How long the board flashes on a win (0 = not at all)
+    spawn_delay: Duration,      // Pause on the settled board before the new tile appears
+    powerups: bool,             // Allow freezing tiles (see POWER-UPS)
+    efficiency: bool,           // Compare the final score with theoretical_score on game over
+    histogram: bool,            // Bar chart of the final board's tile values on game over
+    stream: bool,               // Big score and a milestone ticker, for an audience rather than the player
+    tile_size: (u16, u16),      // Biggest tiles the layout tries, --tile-width/--tile-height
+    block_from: u32,            // Smallest value drawn in the block font, lower ones get the plain label
+    danger: bool,               // Turn the header red with a DANGER warning when the board is nearly stuck
+    checker: bool,              // Tint alternate empty cells like a chessboard
+    coach: bool,                // Mark the corner max tile and warn before a move drags it out
+    careful: bool,              // Ask for the key again before a move that ends the game
+    pipe: bool,                 // Play over stdin/stdout as JSON lines (see PIPE MODE)
+    verify_history: bool,       // Replay every finished game in the history and report, then exit
+    import: Option<ImportedBoard>, // Starting board from another 2048 (see BOARD IMPORT)
+    debug: bool,                // Debug commands ('z' zeroes the score); nothing gets saved
+    force_spawn: Option<(usize, usize, u32)>, // --debug: first spawn (and each 'g') goes here
+    merge_flash: bool,          // Tint the board when a merge reaches FLASH_MIN_TILE
+    ramp: bool,                 // Spawns get harder every RAMP_EVERY moves
+    random_start: Option<f64>,  // Fraction of the board to deal at the start (see deal_random_start)
+    anim_quality: AnimQuality,  // How many slide frames get drawn
+    debounce: Duration,         // Minimum gap between accepted moves (0 = off)
+    move_budget: Option<Duration>, // Longest a move's whole animation may take (see phase_times)
+    idle_timeout: Option<Duration>, // Quit after this long without input
+    garbage: u32,               // Drop an extra 2 every this many moves (0 = off)
+    chill: Option<MoveAxis>,    // Only this axis's two moves are allowed
+    event_log: Option<String>,  // JSON-lines event stream for external tools
+    log_file: Option<String>,   // Where --log sends the `log` records (see LOGGING)
+    log_level: LevelFilter,     // ...and the most detailed level that makes it there
+    watch_file: Option<String>, // Moves written here by anything else get played
+    on_merge_cmd: Option<String>, // Shell command run (in the background) on each merge
+    config: Option<String>,     // Config file to use instead of the default location
+    keys: KeyMap,
+    spawn_weights: Vec<(u32, f64)>, // From the config's [spawn] section
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            letters: false,
+            exponent: false,
+            abbrev: false,
+            header: vec![HeaderField::Score, HeaderField::LastMove],
+            minimal_header: false,
+            combo_notices: false,
+            seed: None,
+            scramble: false,
+            merge_preview: false,
+            minimap: false,
+            sandbox: false,
+            track_optimal: false,
+            theme: Theme::Classic,
+            requested_theme: Theme::Classic,
+            force_color: false,
+            no_color: false,
+            color_depth: None,
+            cursor_color: None,
+            legend: false,
+            score_rule: ScoreRule::Standard,
+            merge_rule: MergeRule::Doubling,
+            no_adjacent_spawn: false,
+            splash: false,
+            fancy_tiles: false,
+            debug_anim: false,
+            instant_settle: false,
+            pause_on_blur: false,
+            save_settings: false,
+            trails: false,
+            undo_highlight: false,
+            coach: false,
+            careful: false,
+            checker: false,
+            shatter: false,
+            reduce_motion: false,
+            spawn_anim: true,
+            danger: false,
+            efficiency: false,
+            histogram: false,
+            powerups: false,
+            celebrate: Duration::ZERO,
+            spawn_delay: Duration::ZERO,
+            block_from: 0,
+            tile_size: (TILE_WIDTH, TILE_HEIGHT),
+            stream: false,
+            pipe: false,
+            verify_history: false,
+            import: None,
+            debug: false,
+            force_spawn: None,
+            merge_flash: false,
+            ramp: false,
+            random_start: None,
+            anim_quality: AnimQuality::High,
+            debounce: Duration::ZERO,
+            move_budget: None,
+            idle_timeout: None,
+            garbage: 0,
+            chill: None,
+            event_log: None,
+            log_file: None,
+            log_level: LevelFilter::Debug,
+            watch_file: None,
+            on_merge_cmd: None,
+            config: None,
+            keys: default_keymap(),
+            spawn_weights: DEFAULT_SPAWN_WEIGHTS.to_vec(),
+        }
+    }
+}
+
+impl Settings {
+    fn apply_config(&mut self, config: &Config) -> Result<(), String> {
+        self.keys = build_keymap(config.section("keys"))?;
+        if let Some(weights) = parse_spawn_weights(config.section("spawn"))? {
+            self.spawn_weights = weights;
+        }
+        for (name, value) in config.section("settings") {
+            // Not in the menu, but save_settings writes it so the next launch can skip detection
+            if name == "color_depth" {
+                self.color_depth = Some(ColorDepth::parse(value)?);
+                continue;
+            }
+            if name == "cursor_color" {
+                self.cursor_color = Some(parse_color(value)?);
+                continue;
+            }
+            let item = MENU.iter().find(|item| item.key() == name).ok_or_else(|| format!("unknown setting '{}'", name))?;
+            item.set(self, value)?;
+        }
+        Ok(())
+    }
 
-                    if moved {
-                        game.spawn_tile();
-                        draw_ui(&mut terminal, &game)?;
+    // --color-depth, then the config's color_depth, then whatever the terminal says
+    fn color_depth(&self) -> ColorDepth {
+        self.color_depth.unwrap_or_else(ColorDepth::detect)
+    }
+
+    // All four, or just the --chill axis
+    fn dirs(&self) -> Vec<Direction> {
+        self.chill.map_or(Direction::ALL.to_vec(), |axis| axis.dirs().to_vec())
+    }
+
+    fn spawn_rules(&self) -> SpawnRules {
+        // Fibonacci boards need their own seeds, and the ramp's doubling makes no sense there
+        if matches!(self.merge_rule, MergeRule::Fibonacci) {
+            let weights = self.merge_rule.spawn_weights().to_vec();
+            return SpawnRules { avoid_adjacent: self.no_adjacent_spawn, weights, ramp: false, start_fill: self.random_start };
+        }
+        SpawnRules {
+            avoid_adjacent: self.no_adjacent_spawn,
+            weights: self.spawn_weights.clone(),
+            ramp: self.ramp,
+            start_fill: self.random_start,
+        }
+    }
+
+    // Layers, lowest first: defaults or the --difficulty preset, the config file, the other flags
+    fn from_args(args: Vec<String>, config: &Config) -> Result<Self, String> {
+        let preset = flag_value(&args, "--difficulty").map(Difficulty::parse);
+        let mut settings = preset.transpose()?.map_or_else(Settings::default, Difficulty::settings);
+        settings.apply_config(config)?;
 
-                        // Simple Game Over Check
-                        let mut full = true;
-                        for r in 0..4 { for c in 0..4 { if game.grid[r][c].is_none() { full = false; } } }
-                        if full { 
-                             game.game_over = true;
-                             draw_ui(&mut terminal, &game)?;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--difficulty" => {
+                    next_value(&mut args, &arg)?; // Already applied above
+                }
+                "--letters" => settings.letters = true,
+                "--exponent" => settings.exponent = true,
+                "--abbrev" => settings.abbrev = true,
+                "--header" => {
+                    let list = next_value(&mut args, &arg)?;
+                    settings.header = list
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(HeaderField::parse)
+                        .collect::<Result<_, _>>()?;
+                }
+                "--minimal-header" => settings.minimal_header = true,
+                "--combo-notices" => settings.combo_notices = true,
+                "--challenge" => {
+                    let challenge = decode_challenge(&next_value(&mut args, &arg)?)?;
+                    settings.seed = challenge.seed;
+                    settings.no_adjacent_spawn = challenge.no_adjacent_spawn;
+                    settings.score_rule = challenge.score_rule;
+                    settings.merge_rule = challenge.merge_rule;
+                }
+                "--seed" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let seed = value.parse().map_err(|_| format!("invalid seed '{}'", value))?;
+                    settings.seed = Some(seed);
+                }
+                "--scramble" => settings.scramble = true,
+                "--merge-preview" => settings.merge_preview = true,
+                "--minimap" => settings.minimap = true,
+                "--sandbox" => settings.sandbox = true,
+                "--track-optimal" => settings.track_optimal = true,
+                "--theme" => settings.theme = Theme::parse(&next_value(&mut args, &arg)?)?,
+                "--force-color" => settings.force_color = true,
+                "--no-color" => settings.no_color = true,
+                "--color-depth" => settings.color_depth = Some(ColorDepth::parse(&next_value(&mut args, &arg)?)?),
+                "--cursor-color" => settings.cursor_color = Some(parse_color(&next_value(&mut args, &arg)?)?),
+                "--legend" => settings.legend = true,
+                "--score-rule" => settings.score_rule = ScoreRule::parse(&next_value(&mut args, &arg)?)?,
+                "--no-adjacent-spawn" => settings.no_adjacent_spawn = true,
+                "--fibonacci" => settings.merge_rule = MergeRule::Fibonacci,
+                "--splash" => settings.splash = true,
+                "--fancy-tiles" => settings.fancy_tiles = true,
+                "--debug-anim" => settings.debug_anim = true,
+                "--instant-settle" => settings.instant_settle = true,
+                "--pause-on-blur" => settings.pause_on_blur = true,
+                "--save-settings" => settings.save_settings = true,
+                "--trails" => settings.trails = true,
+                "--undo-highlight" => settings.undo_highlight = true,
+                "--coach" => settings.coach = true,
+                "--careful" => settings.careful = true,
+                "--checker" => settings.checker = true,
+                "--shatter" => settings.shatter = true,
+                "--reduce-motion" => settings.reduce_motion = true,
+                "--no-spawn-anim" => settings.spawn_anim = false,
+                "--danger" => settings.danger = true,
+                "--efficiency" => settings.efficiency = true,
+                "--histogram" => settings.histogram = true,
+                "--powerups" => settings.powerups = true,
+                "--pipe" => settings.pipe = true,
+                "--verify-history" => settings.verify_history = true,
+                "--import" => settings.import = Some(load_import(&next_value(&mut args, &arg)?)?),
+                "--debug" => settings.debug = true,
+                "--merge-flash" => settings.merge_flash = true,
+                "--ramp" => settings.ramp = true,
+                "--random-start" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let fill: f64 = value.parse().map_err(|_| format!("invalid fill '{}' (fraction of the board)", value))?;
+                    if !(fill > 0.0 && fill <= 1.0) {
+                        return Err(format!("fill {} is outside 0..1", fill));
+                    }
+                    settings.random_start = Some(fill);
+                }
+                "--anim-quality" => settings.anim_quality = AnimQuality::parse(&next_value(&mut args, &arg)?)?,
+                "--config" => settings.config = Some(next_value(&mut args, &arg)?),
+                "--on-merge-cmd" => settings.on_merge_cmd = Some(next_value(&mut args, &arg)?),
+                "--event-log" => settings.event_log = Some(next_value(&mut args, &arg)?),
+                "--log" => settings.log_file = Some(next_value(&mut args, &arg)?),
+                "--log-level" => {
+                    let value = next_value(&mut args, &arg)?;
+                    settings.log_level = value.parse().map_err(|_| format!("invalid log level '{}' (off, error, warn, info, debug, trace)", value))?;
+                }
+                "--watch-file" => settings.watch_file = Some(next_value(&mut args, &arg)?),
+                "--debounce" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let ms = value.parse().map_err(|_| format!("invalid debounce '{}' (milliseconds)", value))?;
+                    settings.debounce = Duration::from_millis(ms);
+                }
+                "--spawn-delay" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let ms = value.parse().map_err(|_| format!("invalid spawn delay '{}' (milliseconds)", value))?;
+                    settings.spawn_delay = Duration::from_millis(ms);
+                }
+                "--move-budget-ms" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let ms = value.parse().map_err(|_| format!("invalid move budget '{}' (milliseconds)", value))?;
+                    settings.move_budget = Some(Duration::from_millis(ms));
+                }
+                "--celebrate" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let ms = value.parse().map_err(|_| format!("invalid celebration '{}' (milliseconds)", value))?;
+                    settings.celebrate = Duration::from_millis(ms);
+                }
+                "--stream" => settings.stream = true,
+                // Only ever bigger: the defaults are the least the block font needs for WIN_TILE
+                "--tile-width" | "--tile-height" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let n: u16 = value.parse().map_err(|_| format!("invalid tile size '{}' (cells)", value))?;
+                    if n > MAX_TILE_SIZE {
+                        return Err(format!("tile size {} is bigger than any terminal (at most {})", n, MAX_TILE_SIZE));
+                    }
+                    if arg == "--tile-width" {
+                        if n < TILE_WIDTH {
+                            return Err(format!("tile width {} is too narrow for the block font (at least {})", n, TILE_WIDTH));
                         }
+                        settings.tile_size.0 = n;
+                    } else {
+                        if n < TILE_HEIGHT {
+                            return Err(format!("tile height {} is too short for the block font (at least {})", n, TILE_HEIGHT));
+                        }
+                        settings.tile_size.1 = n;
                     }
                 }
+                "--block-from" => {
+                    let value = next_value(&mut args, &arg)?;
+                    settings.block_from = value.parse().map_err(|_| format!("invalid tile value '{}'", value))?;
+                }
+                "--idle-timeout" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let secs = value.parse().map_err(|_| format!("invalid idle timeout '{}' (seconds)", value))?;
+                    settings.idle_timeout = Some(Duration::from_secs(secs));
+                }
+                "--chill" => settings.chill = Some(MoveAxis::parse(&next_value(&mut args, &arg)?)?),
+                "--garbage" => {
+                    let value = next_value(&mut args, &arg)?;
+                    settings.garbage = value.parse().map_err(|_| format!("invalid garbage interval '{}' (moves)", value))?;
+                }
+                "--force-spawn" => {
+                    let value = next_value(&mut args, &arg)?;
+                    settings.force_spawn = Some(parse_forced_spawn(&value)?);
+                }
+                other => return Err(format!("unknown option '{}'", other)),
             }
         }
+        if settings.force_spawn.is_some() && !settings.debug {
+            return Err("--force-spawn only works with --debug".to_string());
+        }
+        Ok(settings)
     }
+}
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
-    terminal.show_cursor()?;
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Difficulty {
+    Easy,   // Hardly any 4s
+    Normal, // The defaults
+    Hard,   // 20% 4s and the spawn ramp
+    Insane, // 30% 4s, the ramp, spawns kept away from their twins and a garbage 2 every 10 moves
+}
 
-    Ok(())
+impl Difficulty {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            "insane" => Ok(Difficulty::Insane),
+            other => Err(format!("unknown difficulty '{}' (expected easy, normal, hard or insane)", other)),
+        }
+    }
+
+    fn settings(self) -> Settings {
+        let base = Settings::default();
+        match self {
+            Difficulty::Easy => Settings { spawn_weights: vec![(2, 0.95), (4, 0.05)], ..base },
+            Difficulty::Normal => base,
+            Difficulty::Hard => Settings { spawn_weights: vec![(2, 0.8), (4, 0.2)], ramp: true, ..base },
+            Difficulty::Insane => Settings {
+                spawn_weights: vec![(2, 0.7), (4, 0.3)],
+                ramp: true,
+                no_adjacent_spawn: true,
+                garbage: 10,
+                ..base
+            },
+        }
+    }
+}
+
+// ROW,COL,VALUE with rows and columns counted from 0 and VALUE a power of two
+fn parse_forced_spawn(text: &str) -> Result<(usize, usize, u32), String> {
+    let invalid = || format!("invalid spawn '{}' (expected ROW,COL,VALUE)", text);
+    let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+    let [r, c, val] = parts[..] else { return Err(invalid()) };
+    let (r, c): (usize, usize) = (r.parse().map_err(|_| invalid())?, c.parse().map_err(|_| invalid())?);
+    let val: u32 = val.parse().map_err(|_| invalid())?;
+    if r >= 4 || c >= 4 || val < 2 || !val.is_power_of_two() {
+        return Err(invalid());
+    }
+    Ok((r, c, val))
+}
+
+// Last value given for a flag, for the ones that have to be known before the rest are parsed
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.windows(2).rfind(|pair| pair[0] == flag).map(|pair| pair[1].as_str())
+}
+
+fn next_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} needs a value", flag))
+}
+
+// --- KEY BINDINGS ---
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Action {
+    Move(Direction),
+    Preview(Direction), // Shift + a move key; not bindable on its own
+    Undo,
+    Redo, // Also Ctrl+R
+    Scramble,
+    Practice,
+    Restart, // Only on the game-over screen
+    Replay,  // Likewise
+    Export,
+    Menu,
+    ResetScore, // --debug only
+    ForceSpawn, // --debug only
+    Inspect,
+    Freeze, // --powerups only
+    Header,
+    Quit,
+}
+
+impl Action {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "up" => Ok(Action::Move(Direction::Up)),
+            "down" => Ok(Action::Move(Direction::Down)),
+            "left" => Ok(Action::Move(Direction::Left)),
+            "right" => Ok(Action::Move(Direction::Right)),
+            "undo" => Ok(Action::Undo),
+            "redo" => Ok(Action::Redo),
+            "scramble" => Ok(Action::Scramble),
+            "practice" => Ok(Action::Practice),
+            "restart" => Ok(Action::Restart),
+            "replay" => Ok(Action::Replay),
+            "export" => Ok(Action::Export),
+            "menu" => Ok(Action::Menu),
+            "reset-score" => Ok(Action::ResetScore),
+            "force-spawn" => Ok(Action::ForceSpawn),
+            "inspect" => Ok(Action::Inspect),
+            "freeze" => Ok(Action::Freeze),
+            "header" => Ok(Action::Header),
+            "quit" => Ok(Action::Quit),
+            other => Err(format!("unknown action '{}'", other)),
+        }
+    }
+}
+
+type KeyMap = HashMap<KeyCode, Action>;
+
+fn default_keymap() -> KeyMap {
+    HashMap::from([
+        (KeyCode::Up, Action::Move(Direction::Up)),
+        (KeyCode::Char('w'), Action::Move(Direction::Up)),
+        (KeyCode::Down, Action::Move(Direction::Down)),
+        (KeyCode::Char('s'), Action::Move(Direction::Down)),
+        (KeyCode::Left, Action::Move(Direction::Left)),
+        (KeyCode::Char('a'), Action::Move(Direction::Left)),
+        (KeyCode::Right, Action::Move(Direction::Right)),
+        (KeyCode::Char('d'), Action::Move(Direction::Right)),
+        (KeyCode::Char('u'), Action::Undo),
+        (KeyCode::Char('x'), Action::Scramble),
+        (KeyCode::Char('p'), Action::Practice),
+        (KeyCode::Char('c'), Action::Export),
+        (KeyCode::Char('o'), Action::Menu),
+        (KeyCode::Char('z'), Action::ResetScore),
+        (KeyCode::Char('g'), Action::ForceSpawn),
+        (KeyCode::Char('r'), Action::Replay),
+        (KeyCode::Char('i'), Action::Inspect),
+        (KeyCode::Char('f'), Action::Freeze),
+        (KeyCode::Char('h'), Action::Header),
+        (KeyCode::Enter, Action::Restart),
+        (KeyCode::Char(' '), Action::Restart),
+        (KeyCode::Char('q'), Action::Quit),
+    ])
+}
+
+fn parse_key(name: &str) -> Result<KeyCode, String> {
+    let mut chars = name.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(ch));
+    }
+    match name.to_ascii_lowercase().as_str() {
+        "up" => Ok(KeyCode::Up),
+        "down" => Ok(KeyCode::Down),
+        "left" => Ok(KeyCode::Left),
+        "right" => Ok(KeyCode::Right),
+        "enter" => Ok(KeyCode::Enter),
+        "esc" => Ok(KeyCode::Esc),
+        "space" => Ok(KeyCode::Char(' ')),
+        "tab" => Ok(KeyCode::Tab),
+        "backspace" => Ok(KeyCode::Backspace),
+        _ => Err(format!("unknown key '{}'", name)),
+    }
+}
+
+// Config bindings replace the defaults key by key; one key bound to two actions is an error
+fn build_keymap<'a, I: Iterator<Item = (&'a str, &'a str)>>(bindings: I) -> Result<KeyMap, String> {
+    let mut custom: KeyMap = HashMap::new();
+    for (key, action) in bindings {
+        let code = parse_key(key)?;
+        let action = Action::parse(action)?;
+        if let Some(existing) = custom.insert(code, action)
+            && existing != action
+        {
+            return Err(format!("key '{}' is bound to both {:?} and {:?}", key, existing, action));
+        }
+    }
+    let mut keys = default_keymap();
+    keys.extend(custom);
+    Ok(keys)
+}
+
+// --- SETTINGS MENU ---
+// What 'o' lets you change mid-game; the same names work in the config's [settings] section
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MenuItem {
+    Theme,
+    Animation,
+    Letters,
+    Exponent,
+    Minimap,
+    Legend,
+    MergeFlash,
+    FancyTiles,
+    InstantSettle,
+}
+
+const MENU: [MenuItem; 9] = [
+    MenuItem::Theme,
+    MenuItem::Animation,
+    MenuItem::Letters,
+    MenuItem::Exponent,
+    MenuItem::Minimap,
+    MenuItem::Legend,
+    MenuItem::MergeFlash,
+    MenuItem::FancyTiles,
+    MenuItem::InstantSettle,
+];
+
+impl MenuItem {
+    fn label(self) -> &'static str {
+        match self {
+            MenuItem::Theme => "Theme",
+            MenuItem::Animation => "Animation",
+            MenuItem::Letters => "Letters",
+            MenuItem::Exponent => "Exponents",
+            MenuItem::Minimap => "Minimap",
+            MenuItem::Legend => "Legend",
+            MenuItem::MergeFlash => "Merge flash",
+            MenuItem::FancyTiles => "Fancy tiles",
+            MenuItem::InstantSettle => "Instant settle",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            MenuItem::Theme => "theme",
+            MenuItem::Animation => "anim_quality",
+            MenuItem::Letters => "letters",
+            MenuItem::Exponent => "exponent",
+            MenuItem::Minimap => "minimap",
+            MenuItem::Legend => "legend",
+            MenuItem::MergeFlash => "merge_flash",
+            MenuItem::FancyTiles => "fancy_tiles",
+            MenuItem::InstantSettle => "instant_settle",
+        }
+    }
+
+    // The on/off items, as the Settings field they flip
+    fn flag(self, settings: &mut Settings) -> Option<&mut bool> {
+        match self {
+            MenuItem::Theme | MenuItem::Animation => None,
+            MenuItem::Letters => Some(&mut settings.letters),
+            MenuItem::Exponent => Some(&mut settings.exponent),
+            MenuItem::Minimap => Some(&mut settings.minimap),
+            MenuItem::Legend => Some(&mut settings.legend),
+            MenuItem::MergeFlash => Some(&mut settings.merge_flash),
+            MenuItem::FancyTiles => Some(&mut settings.fancy_tiles),
+            MenuItem::InstantSettle => Some(&mut settings.instant_settle),
+        }
+    }
+
+    fn value(self, settings: &Settings) -> String {
+        match self {
+            MenuItem::Theme => settings.theme.name().to_string(),
+            MenuItem::Animation => settings.anim_quality.name().to_string(),
+            MenuItem::Letters => settings.letters.to_string(),
+            MenuItem::Exponent => settings.exponent.to_string(),
+            MenuItem::Minimap => settings.minimap.to_string(),
+            MenuItem::Legend => settings.legend.to_string(),
+            MenuItem::MergeFlash => settings.merge_flash.to_string(),
+            MenuItem::FancyTiles => settings.fancy_tiles.to_string(),
+            MenuItem::InstantSettle => settings.instant_settle.to_string(),
+        }
+    }
+
+    // Enter, Space or an arrow key: flip a toggle, step a choice on to the next one
+    fn cycle(self, settings: &mut Settings) {
+        match self {
+            MenuItem::Theme => {
+                settings.theme = match settings.theme {
+                    Theme::Classic => Theme::Original { depth: settings.color_depth() },
+                    Theme::Original { .. } => Theme::Mono,
+                    Theme::Mono => Theme::Classic,
+                };
+                settings.requested_theme = settings.theme; // Picked in the menu, so it's a choice
+            }
+            MenuItem::Animation => {
+                settings.anim_quality = match settings.anim_quality {
+                    AnimQuality::Low => AnimQuality::Medium,
+                    AnimQuality::Medium => AnimQuality::High,
+                    AnimQuality::High => AnimQuality::Low,
+                };
+            }
+            _ => {
+                if let Some(on) = self.flag(settings) {
+                    *on = !*on;
+                }
+            }
+        }
+    }
+
+    fn set(self, settings: &mut Settings, value: &str) -> Result<(), String> {
+        match self {
+            MenuItem::Theme => settings.theme = Theme::parse(value)?,
+            MenuItem::Animation => settings.anim_quality = AnimQuality::parse(value)?,
+            _ => {
+                let on = value.parse().map_err(|_| format!("{} should be true or false, not '{}'", self.key(), value))?;
+                if let Some(flag) = self.flag(settings) {
+                    *flag = on;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Keys while the menu is open; it takes all of them, including the move keys
+fn menu_key(view: &mut View, settings: &mut Settings, code: KeyCode) {
+    let Some(selected) = view.menu else { return };
+    match code {
+        KeyCode::Up => view.menu = Some((selected + MENU.len() - 1) % MENU.len()),
+        KeyCode::Down => view.menu = Some((selected + 1) % MENU.len()),
+        KeyCode::Enter | KeyCode::Left | KeyCode::Right | KeyCode::Char(' ') => MENU[selected].cycle(settings),
+        KeyCode::Esc => view.menu = None,
+        code if settings.keys.get(&code) == Some(&Action::Menu) => view.menu = None,
+        _ => {}
+    }
+}
+
+fn render_menu(selected: usize, settings: &Settings) -> Vec<Line<'static>> {
+    MENU.iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let text = format!(" {:<15}{:>8} ", item.label(), item.value(settings));
+            if i == selected {
+                Line::styled(text, Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                Line::from(text)
+            }
+        })
+        .collect()
+}
+
+// Rewrites the [settings] section of the config file, leaving everything else in it alone
+fn save_settings(settings: &Settings) -> io::Result<()> {
+    let Some(path) = settings.config.as_ref().map(PathBuf::from).or_else(default_config_path) else {
+        return Ok(());
+    };
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+    let mut in_settings = false;
+    let kept: Vec<&str> = existing
+        .lines()
+        .filter(|line| {
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_settings = name.trim() == "settings";
+            }
+            !in_settings
+        })
+        .collect();
+
+    let mut text = kept.join("\n").trim_end().to_string();
+    if !text.is_empty() {
+        text.push_str("\n\n");
+    }
+    text.push_str("[settings]\n");
+    // NO_COLOR is about this terminal, not a choice to remember
+    let saved = Settings { theme: settings.requested_theme, ..settings.clone() };
+    for item in MENU {
+        text.push_str(&format!("{} = {}\n", item.key(), item.value(&saved)));
+    }
+    text.push_str(&format!("color_depth = {}\n", settings.color_depth().name()));
+    if let Some(color) = settings.cursor_color {
+        text.push_str(&format!("cursor_color = {}\n", color));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, text)
+}
+
+// --- POWER-UPS ---
+// --powerups: a few freezes per game. A frozen tile neither slides nor merges for
+// FREEZE_MOVES moves, so everything else bumps into it like a wall.
+
+const FREEZE_CHARGES: u32 = 3;
+const FREEZE_MOVES: u32 = 5;
+
+// Called once per move that changed the board
+fn thaw_tiles(grid: &mut Grid) {
+    for tile in grid.iter_mut().flatten().flatten() {
+        tile.frozen = tile.frozen.saturating_sub(1);
+    }
+}
+
+// --- TILE INSPECTOR ---
+// 'i' puts a cursor on the board; the move keys steer it instead of the tiles and the header
+// describes the cell under it. Esc (or 'i' again) goes back to playing.
+
+fn inspect_key(game: &mut Game, view: &mut View, settings: &Settings, code: KeyCode, action: Option<Action>) {
+    let Some(cursor) = view.inspect else { return };
+    match (code, action) {
+        (KeyCode::Esc, _) | (_, Some(Action::Inspect)) => view.inspect = None,
+        (_, Some(Action::Move(dir))) => view.inspect = Some(move_cursor(cursor, dir)),
+        (_, Some(Action::Freeze)) if settings.powerups && game.freezes_left > 0 => {
+            let (r, c) = cursor;
+            if let Some(tile) = &mut game.grid[r][c] {
+                tile.frozen = FREEZE_MOVES;
+                game.freezes_left -= 1;
+                game.replayable = false; // The replay only knows about moves
+                view.inspect = None;
+                let text = format!("Frozen for {} moves ({} left)", FREEZE_MOVES, game.freezes_left);
+                view.show_notice(&text, Duration::from_secs(2));
+            }
+        }
+        _ => {}
+    }
+}
+
+// One cell over, stopping at the edge
+fn move_cursor((r, c): (usize, usize), dir: Direction) -> (usize, usize) {
+    let (dr, dc) = dir.delta();
+    neighbour(r, c, dr, dc).unwrap_or((r, c))
+}
+
+fn inspect_text(game: &Game, (r, c): (usize, usize)) -> String {
+    match game.grid[r][c] {
+        Some(tile) => format!("[{},{}] {}  id {}  tier {}", r, c, tile.val, tile.id, tile.val.trailing_zeros()),
+        None => format!("[{},{}] empty", r, c),
+    }
+}
+
+// --- CONFIG FILE ---
+// TOML-ish: `[section]` headers, `name = value` lines and `#` comments. Quotes are optional.
+
+#[derive(Default)]
+struct Config {
+    entries: Vec<(String, String, String)>, // (section, name, value) in file order
+}
+
+impl Config {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+            } else if let Some((name, value)) = line.split_once('=') {
+                config.entries.push((section.clone(), unquote(name), unquote(value)));
+            } else {
+                return Err(format!("config line {}: expected `name = value`", n + 1));
+            }
+        }
+        Ok(config)
+    }
+
+    fn section<'a>(&'a self, name: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(section, _, _)| section == name)
+            .map(|(_, key, value)| (key.as_str(), value.as_str()))
+    }
+}
+
+fn unquote(text: &str) -> String {
+    let text = text.trim();
+    text.strip_prefix('"').and_then(|t| t.strip_suffix('"')).unwrap_or(text).to_string()
+}
+
+// $XDG_CONFIG_HOME/rust2048/config.toml, falling back to ~/.config
+fn default_config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("rust2048").join("config.toml"))
+}
+
+// An explicit --config must exist; the default one is optional
+fn load_config(explicit: Option<&str>) -> Result<Config, String> {
+    let (path, required) = match explicit {
+        Some(path) => (PathBuf::from(path), true),
+        None => match default_config_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config::default()),
+        },
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => Config::parse(&text).map_err(|err| format!("{}: {}", path.display(), err)),
+        Err(err) if required || err.kind() != io::ErrorKind::NotFound => {
+            Err(format!("can't read config {}: {}", path.display(), err))
+        }
+        Err(_) => Ok(Config::default()),
+    }
+}
+
+// --- PERSISTENCE ---
+// Kept in $XDG_DATA_HOME/rust2048 (or ~/.local/share/rust2048): `scores` uses the same
+// `name = value` format as the config, `games/history.jsonl` gets one line per finished game,
+// `achievements` one unlocked achievement id per line
+
+#[derive(Default)]
+struct Stats {
+    best: u32,       // Highest final score
+    win_streak: u32, // Games in a row that reached WIN_TILE
+    achievements: Achievements,
+}
+
+fn data_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+    Some(base.join("rust2048"))
+}
+
+fn stats_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("scores"))
+}
+
+impl Stats {
+    // A missing or unreadable file just means a fresh start
+    fn load() -> Self {
+        let mut stats = Stats { achievements: Achievements::load(), ..Stats::default() };
+        let Some(text) = stats_path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return stats;
+        };
+        let Ok(config) = Config::parse(&text) else { return stats };
+        for (name, value) in config.section("") {
+            match (name, value.parse()) {
+                ("best", Ok(n)) => stats.best = n,
+                ("win_streak", Ok(n)) => stats.win_streak = n,
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    // Losing the scores file isn't worth interrupting a game over
+    fn save(&self) {
+        let Some(path) = stats_path() else { return };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, format!("best = {}\nwin_streak = {}\n", self.best, self.win_streak));
+    }
+
+    fn record_win(&mut self) {
+        self.win_streak += 1;
+    }
+
+    // A game that already won keeps its streak even if it runs out of moves later
+    fn record_game_over(&mut self, score: u32, won: bool) {
+        self.best = self.best.max(score);
+        if !won {
+            self.win_streak = 0;
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Achievement {
+    First512,
+    HundredMerges,
+    WinWithoutUndo,
+    QuickWin,
+}
+
+const QUICK_WIN: Duration = Duration::from_secs(180);
+
+impl Achievement {
+    const ALL: [Achievement; 4] =
+        [Achievement::First512, Achievement::HundredMerges, Achievement::WinWithoutUndo, Achievement::QuickWin];
+
+    fn id(self) -> &'static str {
+        match self {
+            Achievement::First512 => "first-512",
+            Achievement::HundredMerges => "100-merges",
+            Achievement::WinWithoutUndo => "win-without-undo",
+            Achievement::QuickWin => "quick-win",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Achievement::First512 => "First 512",
+            Achievement::HundredMerges => "100 merges",
+            Achievement::WinWithoutUndo => "Win without undo",
+            Achievement::QuickWin => "Reach 2048 under 3 minutes",
+        }
+    }
+
+    // Checked after every move, so "under 3 minutes" is judged on the move that won
+    fn earned(self, game: &Game) -> bool {
+        match self {
+            Achievement::First512 => game.max_tile() >= 512,
+            Achievement::HundredMerges => game.dir_merges.iter().sum::<u32>() >= 100,
+            Achievement::WinWithoutUndo => game.won && game.undos == 0,
+            Achievement::QuickWin => game.won && game.elapsed() < QUICK_WIN,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Achievements {
+    unlocked: Vec<Achievement>,
+}
+
+fn achievements_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("achievements"))
+}
+
+impl Achievements {
+    // Unknown ids (from a newer version, say) are skipped rather than kept
+    fn load() -> Self {
+        let text = achievements_path().and_then(|path| fs::read_to_string(path).ok()).unwrap_or_default();
+        let unlocked = Achievement::ALL.into_iter().filter(|a| text.lines().any(|line| line.trim() == a.id())).collect();
+        Achievements { unlocked }
+    }
+
+    fn save(&self) {
+        let Some(path) = achievements_path() else { return };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let text: String = self.unlocked.iter().map(|a| format!("{}\n", a.id())).collect();
+        let _ = fs::write(path, text);
+    }
+
+    // The ones this game has just earned for the first time
+    fn check(&mut self, game: &Game) -> Vec<Achievement> {
+        let new: Vec<Achievement> =
+            Achievement::ALL.into_iter().filter(|a| !self.unlocked.contains(a) && a.earned(game)).collect();
+        self.unlocked.extend(&new);
+        new
+    }
+}
+
+struct GameSummary {
+    finished: u64, // Unix seconds
+    seed: u64,
+    score: u32,
+    max_tile: u32,
+    moves: u32,
+    duration: Duration,
+    won: bool,
+    score_rule: ScoreRule,
+    merge_rule: MergeRule,
+    no_adjacent_spawn: bool,
+    garbage: u32,
+    spawn_weights: Vec<(u32, f64)>,
+    ramp: bool,
+    random_start: Option<f64>,
+    log: Option<String>, // Every move as U/D/L/R, for games that replay from the seed alone
+    checksum: Option<u64>, // log_checksum of the seed and log, when there's a log
+}
+
+impl GameSummary {
+    fn new(game: &Game, settings: &Settings) -> Self {
+        GameSummary {
+            finished: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            seed: game.seed,
+            score: game.score,
+            max_tile: game.max_tile(),
+            moves: game.moves,
+            duration: game.elapsed(),
+            won: game.won,
+            score_rule: settings.score_rule,
+            merge_rule: settings.merge_rule,
+            no_adjacent_spawn: settings.no_adjacent_spawn,
+            garbage: settings.garbage,
+            spawn_weights: settings.spawn_weights.clone(),
+            ramp: settings.ramp,
+            random_start: settings.random_start,
+            log: None,
+            checksum: None,
+        }
+        .with_log(game.replayable.then(|| game.move_log.iter().map(|dir| dir.letter()).collect()))
+    }
+
+    fn with_log(self, log: Option<String>) -> Self {
+        let checksum = log.as_deref().map(|log| log_checksum(self.seed, log));
+        GameSummary { log, checksum, ..self }
+    }
+
+    // Just enough JSON to read back what to_json wrote; None for anything else
+    fn from_json(line: &str) -> Option<Self> {
+        let value = |key: &str| {
+            let rest = line.split_once(&format!("\"{}\":", key))?.1;
+            match rest.strip_prefix('"') {
+                Some(quoted) => quoted.split_once('"').map(|(v, _)| v),
+                None => Some(&rest[..rest.find([',', '}']).unwrap_or(rest.len())]),
+            }
+        };
+        let number = |key: &str| value(key)?.parse::<u64>().ok();
+        Some(GameSummary {
+            finished: number("finished")?,
+            seed: number("seed")?,
+            score: number("score")? as u32,
+            max_tile: number("max_tile")? as u32,
+            moves: number("moves")? as u32,
+            duration: Duration::from_millis(number("duration_ms")?),
+            won: value("result")? == "won",
+            score_rule: ScoreRule::parse(value("score_rule")?).ok()?,
+            merge_rule: match value("merge_rule")? {
+                "doubling" => MergeRule::Doubling,
+                "fibonacci" => MergeRule::Fibonacci,
+                _ => return None,
+            },
+            no_adjacent_spawn: value("no_adjacent_spawn")? == "true",
+            garbage: number("garbage")? as u32,
+            // Entries from before these were recorded were played on the defaults
+            spawn_weights: match value("spawn") {
+                Some(weights) => weights
+                    .split(',')
+                    .map(|pair| {
+                        let (v, w) = pair.split_once(':')?;
+                        Some((v.parse().ok()?, w.parse().ok()?))
+                    })
+                    .collect::<Option<_>>()?,
+                None => DEFAULT_SPAWN_WEIGHTS.to_vec(),
+            },
+            ramp: value("ramp") == Some("true"),
+            random_start: value("random_start").and_then(|fill| fill.parse().ok()),
+            log: value("log").map(str::to_string),
+            checksum: value("checksum").and_then(|sum| u64::from_str_radix(sum, 16).ok()),
+        })
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            concat!(
+                r#"{{"finished":{},"seed":{},"score":{},"max_tile":{},"moves":{},"duration_ms":{},"result":"{}","#,
+                r#""settings":{{"score_rule":"{}","merge_rule":"{}","no_adjacent_spawn":{},"garbage":{},"#,
+                r#""spawn":"{}","ramp":{},"random_start":{}}}{}}}"#
+            ),
+            self.finished,
+            self.seed,
+            self.score,
+            self.max_tile,
+            self.moves,
+            self.duration.as_millis(),
+            if self.won { "won" } else { "lost" },
+            self.score_rule.name(),
+            self.merge_rule.name(),
+            self.no_adjacent_spawn,
+            self.garbage,
+            self.spawn_weights.iter().map(|(v, w)| format!("{}:{}", v, w)).collect::<Vec<_>>().join(","),
+            self.ramp,
+            self.random_start.map_or("null".to_string(), |fill| fill.to_string()),
+            match (&self.log, self.checksum) {
+                (Some(log), Some(sum)) => format!(r#","log":"{}","checksum":"{:016x}""#, log, sum),
+                _ => String::new(),
+            },
+        )
+    }
+}
+
+// 64-bit FNV-1a: unlike std's hasher it's fixed, so saved hashes still match next run
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+// Over the seed and the log, so neither can be edited without the other
+fn log_checksum(seed: u64, log: &str) -> u64 {
+    fnv1a(seed.to_le_bytes().into_iter().chain(log.bytes()))
+}
+
+// Checks the checksum, then replays the log from the seed under the entry's recorded rules (and
+// nothing else, so the verifier's own flags can't change the verdict) and checks it lands on the
+// claimed score and move count. Entries without a log (imported boards, forced spawns...) can't pass.
+fn verify_entry(entry: &GameSummary) -> bool {
+    let Some(log) = &entry.log else { return false };
+    if entry.checksum != Some(log_checksum(entry.seed, log)) {
+        return false;
+    }
+    let Some(dirs) = log.chars().map(Direction::from_letter).collect::<Option<Vec<_>>>() else { return false };
+    let rules = Settings {
+        score_rule: entry.score_rule,
+        merge_rule: entry.merge_rule,
+        no_adjacent_spawn: entry.no_adjacent_spawn,
+        garbage: entry.garbage,
+        spawn_weights: entry.spawn_weights.clone(),
+        ramp: entry.ramp,
+        random_start: entry.random_start,
+        ..Settings::default()
+    };
+    let mut replay = Game::new(Some(entry.seed), rules.spawn_rules());
+    for dir in dirs {
+        replay_step(&mut replay, dir, &rules);
+    }
+    replay.score == entry.score && replay.moves == entry.moves
+}
+
+// --verify-history: one line per game saying whether its score stands up
+fn verify_history() {
+    let path = data_dir().map(|dir| dir.join("games").join("history.jsonl"));
+    let text = path.and_then(|path| fs::read_to_string(path).ok()).unwrap_or_default();
+    for (i, line) in text.lines().enumerate() {
+        let verdict = match GameSummary::from_json(line) {
+            None => "unreadable".to_string(),
+            Some(entry) if entry.log.is_none() => format!("seed {} score {}: no move log", entry.seed, entry.score),
+            Some(entry) if verify_entry(&entry) => format!("seed {} score {}: ok", entry.seed, entry.score),
+            Some(entry) => format!("seed {} score {}: DOESN'T REPLAY", entry.seed, entry.score),
+        };
+        println!("{}: {}", i + 1, verdict);
+    }
+}
+
+// Same deal as Stats::save: no history beats a crashed game over
+fn record_summary(summary: &GameSummary) {
+    let Some(dir) = data_dir().map(|dir| dir.join("games")) else { return };
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(dir.join("history.jsonl")) {
+        let _ = writeln!(file, "{}", summary.to_json());
+    }
+}
+
+// --- EVENTS ---
+// Notable moments in a game, fanned out to whatever sinks were asked for on the command line
+
+#[derive(Debug)]
+enum GameEvent {
+    Move { dir: Direction, score: u32 },
+    Merge { value: u32 },
+    Spawn { row: usize, col: usize, value: u32 },
+    GameOver { score: u32, moves: u32 },
+}
+
+trait EventSink {
+    fn emit(&mut self, event: &GameEvent);
+}
+
+fn emit_all(sinks: &mut [Box<dyn EventSink>], event: GameEvent) {
+    match event {
+        GameEvent::GameOver { .. } => info!("{:?}", event),
+        _ => debug!("{:?}", event),
+    }
+    for sink in sinks.iter_mut() {
+        sink.emit(&event);
+    }
+}
+
+fn event_json(event: &GameEvent, timestamp_ms: u128) -> String {
+    let body = match event {
+        GameEvent::Move { dir, score } => format!(r#""event":"move","dir":"{:?}","score":{}"#, dir, score),
+        GameEvent::Merge { value } => format!(r#""event":"merge","value":{}"#, value),
+        GameEvent::Spawn { row, col, value } => {
+            format!(r#""event":"spawn","row":{},"col":{},"value":{}"#, row, col, value)
+        }
+        GameEvent::GameOver { score, moves } => {
+            format!(r#""event":"game_over","score":{},"moves":{}"#, score, moves)
+        }
+    };
+    format!(r#"{{"ts":{},{}}}"#, timestamp_ms, body)
+}
+
+// Appends one JSON object per line, unbuffered so `tail -f` sees events as they happen
+struct FileEventSink {
+    file: File,
+}
+
+impl FileEventSink {
+    fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(FileEventSink { file })
+    }
+}
+
+impl EventSink for FileEventSink {
+    fn emit(&mut self, event: &GameEvent) {
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+        // A full disk shouldn't take the game down with it
+        let _ = writeln!(self.file, "{}", event_json(event, ts));
+    }
+}
+
+// --- LOGGING ---
+// --log FILE: records from the `log` macros go to a file, because stdout and stderr
+// belong to the TUI. Without --log no logger is installed and the macros cost nothing.
+
+struct FileLogger {
+    file: Mutex<File>,
+    level: LevelFilter,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(file, "{} {:<5} {}", ts, record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+fn init_logger(path: &str, level: LevelFilter) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    // The logger lives as long as the process, which is what set_logger needs
+    let logger = Box::leak(Box::new(FileLogger { file: Mutex::new(file), level }));
+    log::set_logger(logger).map_err(|err| io::Error::other(err.to_string()))?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+// Runs a shell command on every merge, e.g. to play a sound
+struct CommandEventSink {
+    command: String,
+    running: Option<process::Child>,
+}
+
+impl CommandEventSink {
+    fn new(command: &str) -> Self {
+        CommandEventSink { command: command.to_string(), running: None }
+    }
+}
+
+impl EventSink for CommandEventSink {
+    fn emit(&mut self, event: &GameEvent) {
+        if !matches!(event, GameEvent::Merge { .. }) {
+            return;
+        }
+        // One at a time: merges that land while the last command is still going are skipped
+        if let Some(child) = &mut self.running
+            && matches!(child.try_wait(), Ok(None))
+        {
+            return;
+        }
+        self.running = process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(process::Stdio::null())
+            .stdout(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .spawn()
+            .ok();
+    }
+}
+
+// --- CHALLENGE CODES ---
+// Base62 of (seed << 16 | board size << 8 | rule bits), after a one-character version.
+// Rule bits: 0 = no adjacent spawn, 1-2 = score rule, 3 = Fibonacci merges.
+
+const BASE62: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const CHALLENGE_VERSION: char = '1';
+
+fn encode_challenge(settings: &Settings) -> String {
+    let rule = match settings.score_rule {
+        ScoreRule::Standard => 0,
+        ScoreRule::Log => 1,
+        ScoreRule::Flat => 2,
+    };
+    let fibonacci = u128::from(matches!(settings.merge_rule, MergeRule::Fibonacci));
+    let flags = u128::from(settings.no_adjacent_spawn) | rule << 1 | fibonacci << 3;
+    let mut n = u128::from(settings.seed.unwrap_or(0)) << 16 | 4 << 8 | flags;
+
+    let mut digits = Vec::new();
+    loop {
+        digits.push(BASE62[(n % 62) as usize] as char);
+        n /= 62;
+        if n == 0 {
+            break;
+        }
+    }
+    std::iter::once(CHALLENGE_VERSION).chain(digits.into_iter().rev()).collect()
+}
+
+fn decode_challenge(code: &str) -> Result<Settings, String> {
+    let invalid = || format!("invalid challenge code '{}'", code);
+    let digits = code.strip_prefix(CHALLENGE_VERSION).filter(|d| !d.is_empty()).ok_or_else(invalid)?;
+    let mut n: u128 = 0;
+    for ch in digits.bytes() {
+        let digit = BASE62.iter().position(|&b| b == ch).ok_or_else(invalid)?;
+        n = n.checked_mul(62).and_then(|n| n.checked_add(digit as u128)).ok_or_else(invalid)?;
+    }
+
+    let seed = u64::try_from(n >> 16).map_err(|_| invalid())?;
+    let size = (n >> 8) & 0xff;
+    if size != 4 {
+        return Err(format!("challenge '{}' is for a {}x{} board; only 4x4 is supported", code, size, size));
+    }
+    if (n >> 4) & 0xf != 0 {
+        return Err(invalid());
+    }
+    let score_rule = match (n >> 1) & 0x3 {
+        0 => ScoreRule::Standard,
+        1 => ScoreRule::Log,
+        2 => ScoreRule::Flat,
+        _ => return Err(invalid()),
+    };
+    let merge_rule = if (n >> 3) & 1 == 1 { MergeRule::Fibonacci } else { MergeRule::Doubling };
+    Ok(Settings {
+        seed: Some(seed),
+        no_adjacent_spawn: n & 1 == 1,
+        score_rule,
+        merge_rule,
+        ..Settings::default()
+    })
+}
+
+// --- BOARD EXPORT ---
+
+const EXPORT_FILE: &str = "rust2048-board.txt"; // Where the board goes when there's no clipboard
+
+// 16 space-separated values, row-major, 0 for an empty cell
+fn board_text(grid: &Grid) -> String {
+    let values: Vec<String> = grid.iter().flatten().map(|cell| cell.map_or(0, |t| t.val).to_string()).collect();
+    values.join(" ")
+}
+
+// Hands the text to whichever clipboard tool the system has
+fn copy_to_clipboard(text: &str) -> bool {
+    const TOOLS: [(&str, &[&str]); 4] = [
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+        ("pbcopy", &[]),
+    ];
+    TOOLS.iter().any(|(cmd, args)| {
+        let Ok(mut child) = process::Command::new(cmd)
+            .args(*args)
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .spawn()
+        else {
+            return false;
+        };
+        let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        child.wait().is_ok_and(|status| status.success()) && written
+    })
+}
+
+// Ok(None) when it reached the clipboard, Ok(Some(path)) when it had to go to a file instead
+fn export_board(text: &str) -> io::Result<Option<&'static str>> {
+    if copy_to_clipboard(text) {
+        return Ok(None);
+    }
+    fs::write(EXPORT_FILE, format!("{}\n", text))?;
+    Ok(Some(EXPORT_FILE))
+}
+
+// --- BOARD IMPORT ---
+// --import FILE starts from a board saved by another 2048: a JSON array of four rows of four
+// numbers (0 = empty), either bare or under "board" or "grid" in an object, which may also
+// carry a "score". Hand-rolled like the rest of the JSON here, so anything fancier is rejected.
+
+#[derive(Clone, Debug)]
+struct ImportedBoard {
+    values: [[u32; 4]; 4],
+    score: Option<u32>,
+}
+
+fn load_import(path: &str) -> Result<ImportedBoard, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path, e))?;
+    parse_import(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn parse_import(text: &str) -> Result<ImportedBoard, String> {
+    let text = text.trim();
+    let (board, score) = if text.starts_with('{') {
+        let value_after = |key: &str| {
+            text.split_once(&format!("\"{}\"", key))
+                .and_then(|(_, rest)| rest.trim_start().strip_prefix(':'))
+                .map(str::trim_start)
+        };
+        let board = value_after("board").or_else(|| value_after("grid")).ok_or("no \"board\" or \"grid\" array")?;
+        let score = match value_after("score") {
+            Some(rest) => {
+                let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+                Some(digits.parse().map_err(|_| "\"score\" isn't a whole number".to_string())?)
+            }
+            None => None,
+        };
+        (board, score)
+    } else {
+        (text, None)
+    };
+
+    let rows = parse_rows(board)?;
+    if rows.len() != 4 || rows.iter().any(|row| row.len() != 4) {
+        return Err("the board must be 4 rows of 4".to_string());
+    }
+    let mut values = [[0; 4]; 4];
+    for (r, row) in rows.iter().enumerate() {
+        for (c, &val) in row.iter().enumerate() {
+            if val == 1 || !(val == 0 || val.is_power_of_two()) {
+                return Err(format!("{} at row {}, column {} isn't a tile value", val, r, c));
+            }
+            values[r][c] = val;
+        }
+    }
+    Ok(ImportedBoard { values, score })
+}
+
+// [[0,2,...],[...],...] up to its closing bracket; whatever follows is someone else's problem
+fn parse_rows(text: &str) -> Result<Vec<Vec<u32>>, String> {
+    let malformed = || "the board isn't an array of arrays of numbers".to_string();
+    let mut rows = Vec::new();
+    let mut row: Option<Vec<u32>> = None;
+    let mut number = String::new();
+    let mut opened = false;
+
+    for ch in text.chars() {
+        match (ch, opened, row.is_some()) {
+            ('[', false, _) => opened = true,
+            ('[', true, false) => row = Some(Vec::new()),
+            (d, true, true) if d.is_ascii_digit() => number.push(d),
+            (',' | ']', true, true) => {
+                if !number.is_empty() {
+                    row.as_mut().ok_or_else(malformed)?.push(number.parse().map_err(|_| malformed())?);
+                    number.clear();
+                }
+                if ch == ']' {
+                    rows.extend(row.take());
+                }
+            }
+            (']', true, false) => return Ok(rows),
+            (',', true, false) => {}
+            (c, _, _) if c.is_whitespace() => {}
+            _ => return Err(malformed()),
+        }
+    }
+    Err(malformed())
+}
+
+// --- RENDERING HELPERS ---
+
+// Distinct colors for each tier: (value, fg, bg)
+const TILE_COLORS: [(u32, Color, Color); 11] = [
+    (2, Color::Black, Color::White),       // White
+    (4, Color::Black, Color::Yellow),      // Yellow
+    (8, Color::White, Color::LightRed),    // Orange-ish
+    (16, Color::White, Color::Red),        // Red
+    (32, Color::White, Color::Magenta),    // Pink
+    (64, Color::White, Color::Blue),       // Blue
+    (128, Color::White, Color::Cyan),      // Cyan
+    (256, Color::Black, Color::LightCyan), // Light Cyan
+    (512, Color::Black, Color::Green),     // Green
+    (1024, Color::White, Color::DarkGray), // Grey
+    (2048, Color::Yellow, Color::Black),   // Black/Gold
+];
+
+type Rgb = (u8, u8, u8);
+
+// The original game's palette, as (value, fg, bg)
+const ORIGINAL_COLORS: [(u32, Rgb, Rgb); 11] = [
+    (2, (119, 110, 101), (238, 228, 218)),    // #eee4da
+    (4, (119, 110, 101), (237, 224, 200)),    // #ede0c8
+    (8, (249, 246, 242), (242, 177, 121)),    // #f2b179
+    (16, (249, 246, 242), (245, 149, 99)),    // #f59563
+    (32, (249, 246, 242), (246, 124, 95)),    // #f67c5f
+    (64, (249, 246, 242), (246, 94, 59)),     // #f65e3b
+    (128, (249, 246, 242), (237, 207, 114)),  // #edcf72
+    (256, (249, 246, 242), (237, 204, 97)),   // #edcc61
+    (512, (249, 246, 242), (237, 200, 80)),   // #edc850
+    (1024, (249, 246, 242), (237, 197, 63)),  // #edc53f
+    (2048, (249, 246, 242), (237, 194, 46)),  // #edc22e
+];
+
+// The 16 standard colours as xterm draws them
+const ANSI16: [(Color, Rgb); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn rgb_distance((r1, g1, b1): Rgb, (r2, g2, b2): Rgb) -> i32 {
+    let d = |x: u8, y: u8| (i32::from(x) - i32::from(y)).pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn nearest_ansi16(rgb: Rgb) -> Color {
+    ANSI16.iter().min_by_key(|&&(_, c)| rgb_distance(rgb, c)).map_or(Color::White, |&(color, _)| color)
+}
+
+// xterm's 256 colours past the first 16: a 6x6x6 cube (16-231), then 24 greys (232-255)
+fn nearest_ansi256((r, g, b): Rgb) -> Color {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let level = |x: u8| (0..6).min_by_key(|&i| (i32::from(LEVELS[i]) - i32::from(x)).abs()).unwrap_or(0);
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+
+    let mean = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+    let step = (mean.saturating_sub(3) / 10).min(23) as u8;
+    let grey = 8 + 10 * step;
+
+    if rgb_distance((r, g, b), (grey, grey, grey)) < rgb_distance((r, g, b), cube) {
+        Color::Indexed(232 + step)
+    } else {
+        Color::Indexed(16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8)
+    }
+}
+
+// Brings any RGB colours in a style down to what the terminal can show
+fn fit_style(style: Style, depth: ColorDepth) -> Style {
+    let fit = |color: Color| match (color, depth) {
+        (Color::Rgb(r, g, b), ColorDepth::Ansi256) => nearest_ansi256((r, g, b)),
+        (Color::Rgb(r, g, b), ColorDepth::Ansi16) => nearest_ansi16((r, g, b)),
+        _ => color,
+    };
+    Style { fg: style.fg.map(fit), bg: style.bg.map(fit), ..style }
+}
+
+fn get_color_style(val: u32, theme: Theme) -> Style {
+    if theme == Theme::Mono {
+        // Without colour the digits do the work; underline marks the big tiles
+        let style = Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED);
+        return if val >= 256 { style.add_modifier(Modifier::UNDERLINED) } else { style };
+    }
+
+    if let Theme::Original { depth } = theme {
+        let ((fr, fg, fb), (br, bg, bb)) = ORIGINAL_COLORS
+            .iter()
+            .find(|&&(v, _, _)| v == val)
+            .map_or(((249, 246, 242), (60, 58, 50)), |&(_, fg, bg)| (fg, bg)); // Super high
+        let style = Style::default().fg(Color::Rgb(fr, fg, fb)).bg(Color::Rgb(br, bg, bb));
+        return fit_style(style, depth).add_modifier(Modifier::BOLD);
+    }
+
+    let (fg, bg) = TILE_COLORS
+        .iter()
+        .find(|&&(v, _, _)| v == val)
+        .map_or((Color::Red, Color::Black), |&(_, fg, bg)| (fg, bg)); // Super high
+    Style::default().fg(fg).bg(bg).add_modifier(Modifier::BOLD)
+}
+
+// Background-only version of a tile's colour, so the board can borrow it for a moment
+fn flash_style(val: u32, theme: Theme) -> Style {
+    let tier = get_color_style(val, theme);
+    match tier.bg {
+        Some(bg) => Style::default().bg(bg),
+        None => tier,
+    }
+}
+
+fn trail_style(theme: Theme) -> Style {
+    match theme {
+        Theme::Classic | Theme::Original { .. } => Style::default().fg(Color::DarkGray),
+        Theme::Mono => Style::default().add_modifier(Modifier::DIM),
+    }
+}
+
+fn header_style(theme: Theme) -> Style {
+    match theme {
+        Theme::Classic | Theme::Original { .. } => {
+            Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD)
+        }
+        Theme::Mono => Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+    }
+}
+
+fn empty_cell_style(theme: Theme) -> Style {
+    match theme {
+        Theme::Classic | Theme::Original { .. } => Style::default().fg(Color::DarkGray),
+        Theme::Mono => Style::default(),
+    }
+}
+
+// --checker: every other empty cell a shade off the terminal background; Mono has no shades to give
+fn checker_style(r: usize, c: usize, theme: Theme) -> Style {
+    let base = empty_cell_style(theme);
+    match theme {
+        Theme::Classic | Theme::Original { .. } if (r + c) % 2 == 1 => base.bg(Color::Indexed(235)),
+        _ => base,
+    }
+}
+
+fn alert_style(theme: Theme) -> Style {
+    match theme {
+        Theme::Classic | Theme::Original { .. } => {
+            Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD)
+        }
+        Theme::Mono => Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+    }
+}
+
+// A colour name ("yellow", "light-cyan"), a 256-colour index or #rrggbb
+fn parse_color(value: &str) -> Result<Color, String> {
+    value.parse().map_err(|_| format!("unknown colour '{}'", value))
+}
+
+// The inspector and freeze cursor. A chosen colour goes on a black backing so it stands out
+// from every tile colour; mono (and NO_COLOR) keeps the reversed alert look.
+fn cursor_style(settings: &Settings) -> Style {
+    match (settings.theme, settings.cursor_color) {
+        (Theme::Mono, _) | (_, None) => alert_style(settings.theme),
+        (theme, Some(color)) => {
+            let style = Style::default().fg(color).bg(Color::Black).add_modifier(Modifier::BOLD);
+            match theme {
+                Theme::Original { depth } => fit_style(style, depth),
+                _ => style,
+            }
+        }
+    }
+}
+
+// 2 -> A, 4 -> B, 8 -> C... (anything past Z stays Z)
+fn value_to_letter(val: u32) -> char {
+    let idx = val.trailing_zeros().saturating_sub(1).min(25) as u8;
+    (b'A' + idx) as char
+}
+
+fn tile_label(val: u32, settings: &Settings) -> String {
+    if settings.letters {
+        value_to_letter(val).to_string()
+    } else if settings.exponent {
+        val.trailing_zeros().to_string()
+    } else if settings.abbrev {
+        abbreviate(val)
+    } else {
+        val.to_string()
+    }
+}
+
+// Thousands and millions, rounded down: 999 -> 999, 1024 -> 1K, 131072 -> 131K, 1048576 -> 1M
+fn abbreviate(val: u32) -> String {
+    match val {
+        0..1_000 => val.to_string(),
+        1_000..1_000_000 => format!("{}K", val / 1_000),
+        _ => format!("{}M", val / 1_000_000),
+    }
+}
+
+fn glyph(ch: char) -> &'static [&'static str; 5] {
+    match ch {
+        '0'..='9' => &FONT[ch as usize - '0' as usize],
+        _ => &LETTER_FONT[ch as usize - 'A' as usize],
+    }
+}
+
+// One cell per character, tinted with the tile's background colour
+// (or its text colour for the black-backed top tiers, which would vanish otherwise)
+fn minimap_cell(cell: Option<Tile>, theme: Theme) -> Span<'static> {
+    match cell {
+        Some(_) if theme == Theme::Mono => Span::raw("■"),
+        Some(tile) => {
+            let style = get_color_style(tile.val, theme);
+            let tier = match style.bg {
+                Some(Color::Black) | None => style.fg.unwrap_or(Color::White),
+                Some(bg) => bg,
+            };
+            Span::styled("■", Style::default().fg(tier))
+        }
+        None => Span::styled("·", empty_cell_style(theme)),
+    }
+}
+
+fn render_minimap(game: &Game, theme: Theme) -> Vec<Line<'static>> {
+    game.grid
+        .iter()
+        .map(|row| Line::from(row.iter().map(|&cell| minimap_cell(cell, theme)).collect::<Vec<_>>()))
+        .collect()
+}
+
+const LEGEND_WIDTH: u16 = 18;
+
+// Every tier from 2 to 131072 in its own colours
+fn render_legend(settings: &Settings) -> Vec<Line<'static>> {
+    (1..=17)
+        .map(|exp| {
+            let val = 1u32 << exp;
+            let sample = Span::styled(format!(" {:^6} ", tile_label(val, settings)), get_color_style(val, settings.theme));
+            if settings.letters || settings.exponent {
+                Line::from(vec![sample, Span::raw(format!(" {}", val))])
+            } else {
+                Line::from(sample)
+            }
+        })
+        .collect()
+}
+
+const STREAM_SCORE_HEIGHT: u16 = 7; // Five rows of block font inside a border
+
+// Newest first, so each milestone pushes the older ones along and eventually off the edge
+fn ticker_text(milestones: &[u32]) -> String {
+    milestones
+        .iter()
+        .rev()
+        .map(|value| format!("Reached {}!", value))
+        .collect::<Vec<_>>()
+        .join("   ")
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+// Builds the header line from the enabled fields, in the order they were given
+fn header_text(game: &Game, view: &View, settings: &Settings) -> String {
+    let mut parts: Vec<String> = if settings.minimal_header {
+        vec!["RUST 2048".to_string()]
+    } else {
+        settings
+            .header
+            .iter()
+            .map(|field| match field {
+                HeaderField::Score => format!("SCORE: {}", game.score),
+                HeaderField::Moves => format!("MOVES: {}", game.moves),
+                HeaderField::Time => format!("TIME: {}", format_elapsed(game.elapsed())),
+                HeaderField::LastMove => game.last_dir.map_or(String::new(), |dir| dir.arrow().to_string()),
+                HeaderField::Progress => {
+                    progress_bar(progress_fraction(game.max_tile(), WIN_TILE))
+                }
+                HeaderField::Seed => format!("SEED: {}", game.seed),
+                HeaderField::Challenge => {
+                    // Restarts deal a new seed, so the code follows the game rather than the flags
+                    format!("CODE: {}", encode_challenge(&Settings { seed: Some(game.seed), ..settings.clone() }))
+                }
+            })
+            .filter(|part| !part.is_empty())
+            .collect()
+    };
+    if settings.track_optimal && game.game_over {
+        parts.push(format!("OPTIMAL: {}%", optimal_percent(game.ai_matched, game.ai_checked)));
+    }
+    // Only the standard rules have a build cost worth comparing against
+    if settings.efficiency
+        && game.game_over
+        && settings.score_rule == ScoreRule::Standard
+        && matches!(settings.merge_rule, MergeRule::Doubling)
+    {
+        let best = theoretical_score(&game.grid);
+        parts.push(format!("EFFICIENCY: {}%", (u64::from(game.score) * 100).checked_div(u64::from(best)).unwrap_or(0)));
+    }
+    if let Some(final_score) = game.sandbox {
+        parts.insert(0, format!("SANDBOX (final {})", final_score));
+    }
+    if settings.debug {
+        parts.insert(0, "DEBUG".to_string());
+    }
+    if settings.coach && !game.game_over {
+        parts.push(format!("SNAKE: {}", snake_score(&game.grid)));
+    }
+    if in_danger(game, settings) {
+        parts.push("DANGER".to_string());
+    }
+    if let Some(cursor) = view.inspect {
+        parts.push(inspect_text(game, cursor));
+    }
+    if let Some(notice) = &view.notice {
+        parts.push(notice.text.clone());
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" {} ", parts.join("   "))
+    }
+}
+
+const DANGER_PRESSURE: f64 = 0.7; // e.g. one empty cell and only two ways to move
+
+// 0 for an empty board with every move open, 1 for a full one that can't move at all
+fn board_pressure(grid: &Grid, settings: &Settings) -> f64 {
+    let dirs = settings.dirs();
+    let free = empty_cells(grid).len() as f64 / 16.0;
+    let legal = dirs.iter().filter(|&&dir| slide_and_merge(grid, dir, settings.merge_rule).moved).count();
+    1.0 - (free + legal as f64 / dirs.len() as f64) / 2.0
+}
+
+// --danger: warn while a live game is close to stuck
+fn in_danger(game: &Game, settings: &Settings) -> bool {
+    settings.danger
+        && !game.game_over
+        && game.sandbox.is_none()
+        && board_pressure(&game.grid, settings) >= DANGER_PRESSURE
+}
+
+// The most a board can have scored: every tile built up from 2s, so a tile v took v/2 merges
+// into 4s, v/4 into 8s and so on, each level adding up to v. An 8 is 4 + 4 + 8 = 16.
+// Spawned 4s skip a level, which is where the real score falls short of this.
+fn theoretical_score(grid: &Grid) -> u32 {
+    grid.iter().flatten().flatten().map(|t| t.val * t.val.ilog2().saturating_sub(1)).sum()
+}
+
+// How far the biggest tile is toward the target, counted in doublings
+fn progress_fraction(max_tile: u32, target: u32) -> f64 {
+    if max_tile < 2 || target < 2 {
+        return 0.0;
+    }
+    (f64::from(max_tile).log2() / f64::from(target).log2()).min(1.0)
+}
+
+fn progress_bar(fraction: f64) -> String {
+    let filled = (fraction * 10.0).round() as usize;
+    format!("[{}{}] {:.0}%", "#".repeat(filled), "-".repeat(10 - filled), fraction * 100.0)
+}
+
+fn header_widget(game: &Game, view: &View, settings: &Settings) -> Paragraph<'static> {
+    let style = if in_danger(game, settings) { alert_style(settings.theme) } else { header_style(settings.theme) };
+    Paragraph::new(header_text(game, view, settings))
+        .style(style)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL))
+}
+
+// A width x height rect in the middle of `outer`. An odd leftover puts the spare
+// row/column after it, so the board never shifts by one between equal-sized terminals.
+fn center_rect(outer: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(outer.width);
+    let height = height.min(outer.height);
+    Rect {
+        x: outer.x + (outer.width - width) / 2,
+        y: outer.y + (outer.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+// Largest tile size, starting from `preferred`, that lets a board_cells x board_cells board
+// fit inside `size`
+fn fit_tile_size(size: Rect, board_cells: u16, preferred: (u16, u16)) -> (u16, u16) {
+    let smaller = TILE_SIZES.iter().copied().filter(|&(w, h)| w <= preferred.0 && h <= preferred.1 && (w, h) != preferred);
+    std::iter::once(preferred)
+        .chain(smaller)
+        .find(|&(w, h)| w.saturating_mul(board_cells) <= size.width && h.saturating_mul(board_cells) <= size.height)
+        .unwrap_or(TILE_SIZES[TILE_SIZES.len() - 1])
+}
+
+// Each block glyph is 3 columns plus a spacer, inside a 1-cell border
+fn block_text_fits(label: &str, tile_w: u16, tile_h: u16) -> bool {
+    tile_h >= TILE_HEIGHT && label.chars().count() as u16 * 4 <= tile_w.saturating_sub(2)
+}
+
+// Plain one-line label, vertically centred inside the tile border
+fn render_plain_text(label: String, tile_h: u16) -> Vec<Line<'static>> {
+    let pad = tile_h.saturating_sub(3) / 2;
+    let mut lines = vec![Line::from(""); pad as usize];
+    lines.push(Line::from(label));
+    lines
+}
+
+// --block-from N keeps the small tiles thin so the big ones stand out
+fn uses_block_text(val: u32, label: &str, tile_w: u16, tile_h: u16, settings: &Settings) -> bool {
+    val >= settings.block_from && block_text_fits(label, tile_w, tile_h)
+}
+
+fn render_block_text(val: u32, settings: &Settings) -> Vec<Line<'static>> {
+    render_block_label(&tile_label(val, settings))
+}
+
+fn render_block_label(label: &str) -> Vec<Line<'static>> {
+    let mut lines = vec![String::new(); 5];
+
+    // Construct the 5 lines of text by stitching glyphs together
+    for ch in label.chars() {
+        for (row, line) in lines.iter_mut().enumerate() {
+            line.push_str(glyph(ch)[row]);
+            line.push(' '); // Spacer between glyphs
+        }
+    }
+
+    lines.into_iter().map(Line::from).collect()
+}
+
+// --- MOVE LOGIC ---
+// Pure board transforms. The animation engine plays these back frame by frame,
+// anything that needs to look ahead (previews, solvers) runs them directly.
+
+type Grid = [[Option<Tile>; 4]; 4];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+    // Position in ALL, for per-direction tallies
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    // For move logs written to disk
+    fn letter(self) -> char {
+        match self {
+            Direction::Up => 'U',
+            Direction::Down => 'D',
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        }
+    }
+
+    fn from_letter(ch: char) -> Option<Self> {
+        Direction::ALL.into_iter().find(|dir| dir.letter() == ch)
+    }
+
+    fn arrow(self) -> char {
+        match self {
+            Direction::Up => '↑',
+            Direction::Down => '↓',
+            Direction::Left => '←',
+            Direction::Right => '→',
+        }
+    }
+
+    fn delta(self) -> (i32, i32) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+        }
+    }
+}
+
+// --chill: the game only moves along one axis, so it ends as soon as that axis is stuck
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MoveAxis {
+    Horizontal,
+    Vertical,
+}
+
+impl MoveAxis {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "horizontal" => Ok(MoveAxis::Horizontal),
+            "vertical" => Ok(MoveAxis::Vertical),
+            other => Err(format!("unknown axis '{}' (expected horizontal or vertical)", other)),
+        }
+    }
+
+    fn dirs(self) -> [Direction; 2] {
+        match self {
+            MoveAxis::Horizontal => [Direction::Left, Direction::Right],
+            MoveAxis::Vertical => [Direction::Up, Direction::Down],
+        }
+    }
+}
+
+// A full board, or under --chill one that can't move along the chosen axis
+fn is_stuck(grid: &Grid, settings: &Settings) -> bool {
+    let full = grid.iter().flatten().all(Option::is_some);
+    full || settings.chill.is_some_and(|axis| axis.dirs().iter().all(|&dir| !slide_and_merge(grid, dir, settings.merge_rule).moved))
+}
+
+// Iteration order matters to prevent overwriting: start from the side tiles move toward
+fn sweep_order(d: i32) -> Vec<usize> {
+    if d > 0 { (0..4).rev().collect() } else { (0..4).collect() }
+}
+
+fn neighbour(r: usize, c: usize, dr: i32, dc: i32) -> Option<(usize, usize)> {
+    let nr = r as i32 + dr;
+    let nc = c as i32 + dc;
+    if (0..4).contains(&nr) && (0..4).contains(&nc) {
+        Some((nr as usize, nc as usize))
+    } else {
+        None
+    }
+}
+
+fn occupied(grid: &Grid) -> Vec<(usize, usize)> {
+    (0..4).flat_map(|r| (0..4).map(move |c| (r, c))).filter(|&(r, c)| grid[r][c].is_some()).collect()
+}
+
+// One slide frame: every tile whose next cell was empty moves one slot
+fn slide_step(grid: &Grid, dr: i32, dc: i32) -> Option<Grid> {
+    let mut step_moved = false;
+    let mut next_grid = *grid;
+
+    for &r in &sweep_order(dr) {
+        for &c in &sweep_order(dc) {
+            if let Some(tile) = grid[r][c]
+                && tile.frozen == 0 // Frozen tiles stay put, and anything sliding stops at them
+                && let Some((nr, nc)) = neighbour(r, c, dr, dc)
+                && grid[nr][nc].is_none()
+            {
+                next_grid[nr][nc] = Some(tile);
+                next_grid[r][c] = None;
+                step_moved = true;
+            }
+        }
+    }
+
+    if step_moved { Some(next_grid) } else { None }
+}
+
+struct MergePass {
+    grid: Grid,
+    pairs: Vec<(usize, usize)>, // (moving id, absorbed id) for each merge
+    merged: Vec<(u32, u32)>,    // The two values that went into each merge
+}
+
+// Decides whether two values combine, and into what
+#[cfg(test)]
+type MergePredicate = fn(u32, u32) -> Option<u32>;
+
+#[derive(Clone, Copy, Debug)]
+enum MergeRule {
+    Doubling,               // Equal tiles combine: 2+2=4
+    Fibonacci,              // Neighbouring Fibonacci numbers combine: 1+1=2, 1+2=3, 2+3=5
+    // Anything else, for tests that need the merge pass to follow a rule of their own. A
+    // predicate has no name to save, so the validator, challenge codes and --verify-history
+    // couldn't follow it in a real game
+    #[cfg(test)]
+    Custom(MergePredicate),
+}
+
+impl MergeRule {
+    // New tiles, and what the solver expects to see spawn
+    fn spawn_weights(self) -> [(u32, f64); 2] {
+        match self {
+            MergeRule::Doubling => DEFAULT_SPAWN_WEIGHTS,
+            #[cfg(test)]
+            MergeRule::Custom(_) => DEFAULT_SPAWN_WEIGHTS,
+            MergeRule::Fibonacci => [(1, 0.9), (2, 0.1)],
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            MergeRule::Doubling => "doubling",
+            MergeRule::Fibonacci => "fibonacci",
+            #[cfg(test)]
+            MergeRule::Custom(_) => "custom",
+        }
+    }
+}
+
+// The tile two values combine into, or None if they don't merge
+fn can_merge(a: u32, b: u32, rule: MergeRule) -> Option<u32> {
+    match rule {
+        MergeRule::Doubling => (a == b).then(|| a + b),
+        MergeRule::Fibonacci => {
+            let (lo, hi) = (a.min(b), a.max(b));
+            let (mut x, mut y) = (1u32, 1u32);
+            while x <= lo {
+                if (x, y) == (lo, hi) {
+                    return Some(a + b);
+                }
+                let Some(next) = x.checked_add(y) else { break };
+                (x, y) = (y, next);
+            }
+            None
+        }
+        #[cfg(test)]
+        MergeRule::Custom(predicate) => predicate(a, b),
+    }
+}
+
+// Single merge sweep over a fully slid board
+fn merge_step(grid: &Grid, dr: i32, dc: i32, rule: MergeRule) -> MergePass {
+    let mut next_grid = *grid;
+    let mut merged_mask = [[false; 4]; 4]; // Prevent double merges
+    let mut pairs = Vec::new();
+    let mut merged = Vec::new();
+
+    for &r in &sweep_order(dr) {
+        for &c in &sweep_order(dc) {
+            if let Some(tile) = grid[r][c]
+                && let Some((nr, nc)) = neighbour(r, c, dr, dc)
+                && let Some(target) = next_grid[nr][nc]
+                && tile.frozen == 0 && target.frozen == 0
+                && !merged_mask[nr][nc] && !merged_mask[r][c]
+                && let Some(val) = can_merge(tile.val, target.val, rule)
+            {
+                // Merge happens
+                next_grid[nr][nc] = Some(Tile { val, id: tile.id, frozen: 0 });
+                next_grid[r][c] = None;
+                merged_mask[nr][nc] = true;
+                pairs.push((tile.id, target.id));
+                merged.push((tile.val, target.val));
+            }
+        }
+    }
+
+    MergePass { grid: next_grid, pairs, merged }
+}
+
+// Closes the gaps a merge pass leaves behind
+fn snap_step(grid: &Grid, dr: i32, dc: i32) -> Option<Grid> {
+    let mut snap_grid = *grid;
+    let mut snapped = false;
+    for &r in &sweep_order(dr) {
+        for &c in &sweep_order(dc) {
+            if let Some(tile) = snap_grid[r][c]
+                && tile.frozen == 0
+                && let Some((nr, nc)) = neighbour(r, c, dr, dc)
+                && snap_grid[nr][nc].is_none()
+            {
+                snap_grid[nr][nc] = Some(tile);
+                snap_grid[r][c] = None;
+                snapped = true;
+            }
+        }
+    }
+    if snapped { Some(snap_grid) } else { None }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ScoreRule {
+    Standard, // Value of the new tile (equal to the sum of the two that merged)
+    Log,      // log2 of the new tile: a 512 is worth 9
+    Flat,     // One point per merge
+}
+
+impl ScoreRule {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "standard" => Ok(ScoreRule::Standard),
+            "log" => Ok(ScoreRule::Log),
+            "flat" => Ok(ScoreRule::Flat),
+            other => Err(format!("unknown score rule '{}' (expected standard, log or flat)", other)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ScoreRule::Standard => "standard",
+            ScoreRule::Log => "log",
+            ScoreRule::Flat => "flat",
+        }
+    }
+}
+
+fn score_for_merge(a: u32, b: u32, rule: ScoreRule) -> u32 {
+    match rule {
+        ScoreRule::Standard => a + b,
+        ScoreRule::Log => (a + b).ilog2(),
+        ScoreRule::Flat => 1,
+    }
+}
+
+fn merge_points(merged: &[(u32, u32)], rule: ScoreRule) -> u32 {
+    merged.iter().map(|&(a, b)| score_for_merge(a, b, rule)).sum()
+}
+
+struct MoveOutcome {
+    grid: Grid,
+    moved: bool,
+    pairs: Vec<(usize, usize)>,
+    merged: Vec<(u32, u32)>,
+}
+
+// The corner holding the biggest tile, if it's in one
+fn max_corner(grid: &Grid) -> Option<(usize, usize)> {
+    let max = grid.iter().flatten().flatten().map(|t| t.val).max()?;
+    [(0, 0), (0, 3), (3, 0), (3, 3)].into_iter().find(|&(r, c)| grid[r][c].is_some_and(|t| t.val == max))
+}
+
+// --coach: 0-100 for how close the board is to the "snake", every tile no bigger than the one
+// before it along a path that zigzags row by row (or column by column) out of a corner. Empty
+// cells are skipped. Each step up costs its rise, as a share of all the rises and falls along
+// the path, and the board gets the best of the eight possible snakes. A shuffled board climbs
+// about half the time, so that's where the scale bottoms out.
+fn snake_score(grid: &Grid) -> u8 {
+    let mut best = 0.0;
+    for transpose in [false, true] {
+        for flip_r in [false, true] {
+            for flip_c in [false, true] {
+                let values: Vec<f64> = (0..16)
+                    .filter_map(|i| {
+                        let (r, c) = (i / 4, if (i / 4) % 2 == 0 { i % 4 } else { 3 - i % 4 });
+                        let (r, c) = (if flip_r { 3 - r } else { r }, if flip_c { 3 - c } else { c });
+                        let (r, c) = if transpose { (c, r) } else { (r, c) };
+                        grid[r][c].map(|tile| log_val(Some(tile)))
+                    })
+                    .collect();
+                let total: f64 = values.windows(2).map(|w| (w[0] - w[1]).abs()).sum();
+                let rises: f64 = values.windows(2).map(|w| (w[1] - w[0]).max(0.0)).sum();
+                let score = if total == 0.0 { 1.0 } else { 1.0 - rises / total };
+                best = f64::max(best, score);
+            }
+        }
+    }
+    ((best - 0.5).max(0.0) * 200.0).round() as u8
+}
+
+// True if the move leaves that corner without a tile at least as big (merging in place is fine)
+fn displaces_corner_max(grid: &Grid, dir: Direction, rule: MergeRule) -> bool {
+    let Some((r, c)) = max_corner(grid) else { return false };
+    let max = grid[r][c].map_or(0, |t| t.val);
+    let outcome = slide_and_merge(grid, dir, rule);
+    outcome.moved && outcome.grid[r][c].is_none_or(|t| t.val < max)
+}
+
+// --careful: the game is over once the board is full after the spawns, so a move ends it when
+// it leaves no more free cells than are about to be filled (--garbage can add a second tile)
+fn ends_game(game: &Game, settings: &Settings, dir: Direction) -> bool {
+    let outcome = slide_and_merge(&game.grid, dir, settings.merge_rule);
+    if game.sandbox.is_some() || !outcome.moved || !settings.dirs().contains(&dir) {
+        return false;
+    }
+    let garbage = settings.garbage > 0 && (game.moves + 1).is_multiple_of(settings.garbage);
+    empty_cells(&outcome.grid).len() <= 1 + usize::from(garbage)
+}
+
+// The whole move in one go, exactly as animate_move would leave the board
+fn slide_and_merge(grid: &Grid, dir: Direction, rule: MergeRule) -> MoveOutcome {
+    let (dr, dc) = dir.delta();
+    let mut grid = *grid;
+    let mut moved = false;
+
+    for _ in 0..4 {
+        match slide_step(&grid, dr, dc) {
+            Some(next) => { grid = next; moved = true; }
+            None => break,
+        }
+    }
+
+    let pass = merge_step(&grid, dr, dc, rule);
+    if !pass.pairs.is_empty() {
+        grid = pass.grid;
+        moved = true;
+        for _ in 0..4 {
+            match snap_step(&grid, dr, dc) {
+                Some(next) => grid = next,
+                None => break,
+            }
+        }
+    }
+
+    MoveOutcome { grid, moved, pairs: pass.pairs, merged: pass.merged }
+}
+
+// --- AI SOLVER ---
+// Shallow expectimax: try each move, average over every possible spawn, then
+// take the best follow-up move and score the result with a board heuristic.
+
+const AI_DEPTH: u32 = 1; // Follow-up moves searched after the first one
+
+// True if any orthogonal neighbour of (r, c) holds `val`
+fn touches_value(grid: &Grid, r: usize, c: usize, val: u32) -> bool {
+    Direction::ALL.iter().any(|dir| {
+        let (dr, dc) = dir.delta();
+        neighbour(r, c, dr, dc).is_some_and(|(nr, nc)| grid[nr][nc].is_some_and(|t| t.val == val))
+    })
+}
+
+// Row-major list of free cells. spawn_tile indexes into this with the seeded RNG, so the
+// order is part of what makes a seed reproducible: changing it moves every spawn and breaks
+// --challenge codes, replays and saved event logs.
+fn empty_cells(grid: &Grid) -> Vec<(usize, usize)> {
+    (0..4)
+        .flat_map(|r| (0..4).map(move |c| (r, c)))
+        .filter(|&(r, c)| grid[r][c].is_none())
+        .collect()
+}
+
+fn log_val(cell: Option<Tile>) -> f64 {
+    cell.map_or(0.0, |t| t.val.trailing_zeros() as f64)
+}
+
+// Rewards free space and rows/columns that rise or fall steadily toward an edge
+fn evaluate(grid: &Grid) -> f64 {
+    let empty = empty_cells(grid).len() as f64;
+
+    let rows = grid.iter().map(|row| row.map(log_val));
+    let cols = (0..4).map(|c| grid.map(|row| log_val(row[c])));
+
+    let mut monotonic = 0.0;
+    let mut rough = 0.0;
+    for line in rows.chain(cols) {
+        let (mut up, mut down) = (0.0, 0.0);
+        for pair in line.windows(2) {
+            let diff = pair[1] - pair[0];
+            if diff > 0.0 { up += diff } else { down -= diff }
+            rough += diff.abs();
+        }
+        monotonic -= f64::min(up, down);
+    }
+
+    empty * 2.7 + monotonic * 1.0 - rough * 0.1
+}
+
+fn chance_value(grid: &Grid, depth: u32, rule: MergeRule, dirs: &[Direction]) -> f64 {
+    let empty = empty_cells(grid);
+    if empty.is_empty() {
+        return evaluate(grid);
+    }
+    let mut total = 0.0;
+    for &(r, c) in &empty {
+        for (val, p) in rule.spawn_weights() {
+            let mut next = *grid;
+            next[r][c] = Some(Tile { val, id: 0, frozen: 0 });
+            total += p * max_value(&next, depth, rule, dirs);
+        }
+    }
+    total / empty.len() as f64
+}
+
+fn max_value(grid: &Grid, depth: u32, rule: MergeRule, dirs: &[Direction]) -> f64 {
+    if depth == 0 {
+        return evaluate(grid);
+    }
+    dirs.iter()
+        .map(|&dir| slide_and_merge(grid, dir, rule))
+        .filter(|outcome| outcome.moved)
+        .map(|outcome| chance_value(&outcome.grid, depth - 1, rule, dirs))
+        .fold(None, |best: Option<f64>, v| Some(best.map_or(v, |b| b.max(v))))
+        .unwrap_or(f64::MIN / 2.0) // Stuck boards are as bad as it gets
+}
+
+// Equal scores go to whichever comes first here, so replays always pick the same move
+const AI_TIE_ORDER: [Direction; 4] = [Direction::Up, Direction::Left, Direction::Down, Direction::Right];
+
+// The solver's pick for this board, or None if nothing can move. Following it can't loop:
+// merges keep the board's total and every move that changes the board spawns on top, so the
+// total only grows and no position (or board_hash) ever comes round again. Under --chill it
+// only looks along the axis, follow-ups included.
+fn best_move(grid: &Grid, settings: &Settings) -> Option<Direction> {
+    let (rule, dirs) = (settings.merge_rule, settings.dirs());
+    let mut best: Option<(Direction, f64)> = None;
+    for dir in AI_TIE_ORDER.into_iter().filter(|dir| dirs.contains(dir)) {
+        let outcome = slide_and_merge(grid, dir, rule);
+        if !outcome.moved {
+            continue;
+        }
+        let value = chance_value(&outcome.grid, AI_DEPTH, rule, &dirs);
+        if best.is_none_or(|(_, b)| value > b) {
+            best = Some((dir, value));
+        }
+    }
+    best.map(|(dir, _)| dir)
+}
+
+// Share of the player's moves that matched the solver, rounded down
+fn optimal_percent(matched: u32, checked: u32) -> u32 {
+    (matched * 100).checked_div(checked).unwrap_or(0)
+}
+
+// --- EVENT LOOP ---
+// Two background threads feed one channel: one reads the terminal, the other ticks every TICK.
+// Everything that waits (the main loop, animation frames, the splash, replays) waits on that
+// channel, so the clock and notices update on ticks whether or not keys arrive, and nothing
+// on the input path sleeps. Dropping Events stops both threads and joins them.
+
+const TICK: Duration = Duration::from_millis(50);
+
+enum Incoming {
+    Input(Event),
+    Tick,
+    Failed(io::Error), // The input thread hit a terminal error and stopped
+}
+
+struct Events {
+    rx: mpsc::Receiver<Incoming>,
+    stop: Arc<AtomicBool>,
+    threads: Vec<thread::JoinHandle<()>>,
+    #[cfg(test)]
+    keys: mpsc::Sender<Incoming>, // Lets a test type into a headless game
+}
+
+impl Events {
+    fn start() -> Self {
+        Events::spawn(true)
+    }
+
+    // Ticks but no terminal reads, so the game can run on a TestBackend
+    #[cfg(test)]
+    fn headless() -> Self {
+        Events::spawn(false)
+    }
+
+    #[cfg(test)]
+    fn press(&self, code: KeyCode) {
+        let _ = self.keys.send(Incoming::Input(Event::Key(KeyEvent::new(code, KeyModifiers::NONE))));
+    }
+
+    fn spawn(read_terminal: bool) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut threads = Vec::new();
+
+        if read_terminal {
+            let (tx, stop) = (tx.clone(), Arc::clone(&stop));
+            threads.push(thread::spawn(move || {
+                // poll() rather than a bare read() so a stop is noticed within a tick
+                while !stop.load(Ordering::Relaxed) {
+                    let incoming = match event::poll(TICK) {
+                        Ok(false) => continue,
+                        Ok(true) => event::read().map_or_else(Incoming::Failed, Incoming::Input),
+                        Err(err) => Incoming::Failed(err),
+                    };
+                    let failed = matches!(incoming, Incoming::Failed(_));
+                    if tx.send(incoming).is_err() || failed {
+                        break;
+                    }
+                }
+            }));
+        }
+        #[cfg(test)]
+        let keys = tx.clone();
+        let ticker_stop = Arc::clone(&stop);
+        threads.push(thread::spawn(move || {
+            while !ticker_stop.load(Ordering::Relaxed) {
+                thread::sleep(TICK);
+                if tx.send(Incoming::Tick).is_err() {
+                    break;
+                }
+            }
+        }));
+        Events {
+            rx,
+            stop,
+            threads,
+            #[cfg(test)]
+            keys,
+        }
+    }
+
+    // Whatever comes next, input or tick
+    fn next(&self) -> io::Result<Incoming> {
+        self.rx.recv().map_err(|_| io::Error::other("the event threads stopped"))
+    }
+
+    // The next input, however long it takes
+    fn read(&self) -> io::Result<Event> {
+        loop {
+            match self.next()? {
+                Incoming::Input(event) => return Ok(event),
+                Incoming::Tick => {}
+                Incoming::Failed(err) => return Err(err),
+            }
+        }
+    }
+
+    // The next input within `timeout`, or None
+    fn input(&self, timeout: Duration) -> io::Result<Option<Event>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(Incoming::Input(event)) => return Ok(Some(event)),
+                Ok(Incoming::Tick) => {}
+                Ok(Incoming::Failed(err)) => return Err(err),
+                Err(mpsc::RecvTimeoutError::Timeout) => return Ok(None),
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::other("the event threads stopped"));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Events {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+// --- ANIMATION ENGINE ---
+
+const MAX_QUEUED_MOVES: usize = 4; // Anything typed beyond this during an animation is dropped
+
+// Waits out one animation frame while still listening, so moves typed meanwhile get queued
+fn wait_frame(events: &Events, view: &mut View, settings: &Settings, frame: Duration) -> io::Result<()> {
+    let deadline = Instant::now() + frame;
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(());
+        }
+        if let Some(Event::Key(key)) = events.input(deadline - now)? {
+            match key_action(&key, settings) {
+                Some(Action::Quit) => view.quit_requested = true,
+                Some(Action::Move(dir)) if view.queued_moves.len() < MAX_QUEUED_MOVES => {
+                    view.queued_moves.push_back(dir);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum AnimQuality {
+    Low,    // Jump straight to the slid board
+    Medium, // Every other cell
+    High,   // Every cell a tile passes through
+}
+
+impl AnimQuality {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "low" => Ok(AnimQuality::Low),
+            "medium" => Ok(AnimQuality::Medium),
+            "high" => Ok(AnimQuality::High),
+            other => Err(format!("unknown animation quality '{}' (expected low, medium or high)", other)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            AnimQuality::Low => "low",
+            AnimQuality::Medium => "medium",
+            AnimQuality::High => "high",
+        }
+    }
+}
+
+// Drawn frames for the longest possible slide (board_size - 1 cells)
+fn slide_frames(board_size: usize, quality: AnimQuality) -> usize {
+    let travel = board_size.saturating_sub(1).max(1);
+    match quality {
+        AnimQuality::Low => 1,
+        AnimQuality::Medium => travel.div_ceil(2),
+        AnimQuality::High => travel,
+    }
+}
+
+// draw_ui for the frames in between moves. A write that fails there (a hiccup on a flaky SSH
+// link, say) gets one clear and redraw before the error goes up to run() and out through
+// main's terminal restore.
+fn draw_frame<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    game: &Game,
+    view: &View,
+    settings: &Settings,
+) -> io::Result<()> {
+    if draw_ui(terminal, game, view, settings).is_ok() {
+        return Ok(());
+    }
+    terminal.clear()?;
+    draw_ui(terminal, game, view, settings)
+}
+
+// Draws one animation frame, or just records it under --debug-anim
+fn show_frame<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    events: &Events,
+    game: &mut Game,
+    view: &mut View,
+    settings: &Settings,
+    hold: Duration,
+) -> io::Result<()> {
+    if settings.debug_anim {
+        game.anim_frames.push(game.grid);
+        return Ok(());
+    }
+    if settings.reduce_motion {
+        return Ok(()); // run() draws the finished move
+    }
+    draw_frame(terminal, game, view, settings)?;
+    wait_frame(events, view, settings, hold)
+}
+
+// Steps through the recorded frames with '.' and ','; any other key goes back to the game
+fn step_frames<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    events: &Events,
+    game: &mut Game,
+    view: &mut View,
+    settings: &Settings,
+) -> io::Result<()> {
+    let Some(last) = game.anim_frames.len().checked_sub(1) else { return Ok(()) };
+    let real = game.grid;
+    let mut i = last;
+    loop {
+        game.grid = game.anim_frames[i];
+        view.show_notice(&format!("FRAME {}/{}", i + 1, last + 1), Duration::from_secs(3600));
+        draw_ui(terminal, game, view, settings)?;
+        if let Event::Key(key) = events.read()? {
+            match key.code {
+                KeyCode::Char('.') => i = (i + 1).min(last),
+                KeyCode::Char(',') => i = i.saturating_sub(1),
+                _ => break,
+            }
+        }
+    }
+    game.grid = real;
+    view.notice = None;
+    Ok(())
+}
+
+const SLIDE_FRAME: Duration = Duration::from_millis(50);
+const MERGE_FRAME: Duration = Duration::from_millis(50);
+const FLASH_FRAME: Duration = Duration::from_millis(120);
+const SPAWN_FRAME: Duration = Duration::from_millis(50);
+
+// How long each of a move's frames is held
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct PhaseTimes {
+    slide: Duration, // Per drawn slide frame
+    merge: Duration,
+    flash: Duration,
+    spawn: Duration,
+    delay: Duration, // --spawn-delay, held on the settled board before the spawn
+}
+
+// Phases the move won't run get no time at all. --move-budget-ms: the ones it will run keep
+// their proportions but are scaled down together until the whole move fits. Moves that already
+// fit are left alone. The spawn delay is asked for on purpose, so it isn't part of the cap.
+fn phase_times(slides: usize, merge: bool, flash: bool, spawn: bool, delay: Duration, budget: Option<Duration>) -> PhaseTimes {
+    let runs = |runs: bool, frame: Duration| if runs { frame } else { Duration::ZERO };
+    let full = PhaseTimes {
+        slide: runs(slides > 0, SLIDE_FRAME),
+        merge: runs(merge, MERGE_FRAME),
+        flash: runs(flash, FLASH_FRAME),
+        spawn: runs(spawn, SPAWN_FRAME),
+        delay,
+    };
+    let total = full.slide * slides as u32 + full.merge + full.flash + full.spawn;
+    let Some(budget) = budget.filter(|&budget| total > budget) else { return full };
+    // Rounded down, so the scaled phases can only come in under the budget
+    let scale = |frame: Duration| Duration::from_nanos((frame.as_nanos() * budget.as_nanos() / total.as_nanos()) as u64);
+    PhaseTimes { slide: scale(full.slide), merge: scale(full.merge), flash: scale(full.flash), spawn: scale(full.spawn), delay }
+}
+
+// Moves grid visually step-by-step, then spawns the next tile. Frame timeline:
+//   slide steps   50ms each (fewer with --anim-quality)
+//   merge         50ms, then the --merge-flash tint (120ms) if earned
+//   spawn         50ms with the new tile at half size; the full-size board comes from run()
+// --move-budget-ms squeezes the lot into a fixed time, see phase_times.
+fn animate_move<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    events: &Events,
+    game: &mut Game,
+    view: &mut View,
+    settings: &Settings,
+    dir: Direction,
+) -> io::Result<bool> {
+    let (dr, dc) = dir.delta();
+    let mut something_moved = false;
+    game.anim_frames.clear();
+
+    // 1. VISUAL SLIDE
+    // Every step is applied; the quality only decides how many of them get drawn
+    let mut steps = Vec::new();
+    let mut grid = game.grid;
+    let mut visited = occupied(&grid); // Every cell a tile sat in at some point, for --trails
+    while let Some(next_grid) = slide_step(&grid, dr, dc) {
+        grid = next_grid;
+        steps.push(next_grid);
+        visited.extend(occupied(&grid));
+    }
+    let stride = steps.len().div_ceil(slide_frames(game.grid.len(), settings.anim_quality));
+    let drawn = |i: usize| (i + 1).is_multiple_of(stride) || i + 1 == steps.len();
+
+    // The merge only depends on where the slide ends, so the whole move can be timed up front
+    let pass = merge_step(&grid, dr, dc, settings.merge_rule);
+    let best = pass.merged.iter().map(|&(a, b)| a + b).max().unwrap_or(0);
+    let flashes = settings.merge_flash && !settings.reduce_motion && !settings.debug_anim && best >= FLASH_MIN_TILE;
+    let moves = !steps.is_empty() || !pass.pairs.is_empty();
+    // --reduce-motion drops the spawn delay along with the other waits
+    let delay = if moves && !settings.reduce_motion && !settings.debug_anim { settings.spawn_delay } else { Duration::ZERO };
+    let times = phase_times(
+        (0..steps.len()).filter(|&i| drawn(i)).count(),
+        !pass.pairs.is_empty(),
+        flashes,
+        moves && settings.spawn_anim,
+        delay,
+        settings.move_budget,
+    );
+
+    for (i, next_grid) in steps.iter().enumerate() {
+        game.grid = *next_grid;
+        something_moved = true;
+        if drawn(i) {
+            show_frame(terminal, events, game, view, settings, times.slide)?;
+        }
+    }
+
+    // 2. MERGE LOGIC
+    game.last_merged = pass.merged.iter().map(|&(a, b)| a + b).collect();
+    visited.extend(occupied(&pass.grid));
+
+    // Snap slide after merge (cleanup gaps)
+    let settle = |grid: &mut Grid| {
+        for _ in 0..4 {
+            match snap_step(grid, dr, dc) {
+                Some(snap_grid) => *grid = snap_grid,
+                None => break,
+            }
+        }
+    };
+
+    if !pass.pairs.is_empty() {
+        game.grid = pass.grid;
+        game.score += merge_points(&pass.merged, settings.score_rule);
+        something_moved = true;
+        // Same final board either way; --instant-settle just never shows the gaps
+        if settings.instant_settle {
+            settle(&mut game.grid);
+        }
+        show_frame(terminal, events, game, view, settings, times.merge)?;
+
+        // Celebrate milestones with a quick tint in the biggest new tile's colour
+        if flashes {
+            view.flash = Some(best);
+            draw_frame(terminal, game, view, settings)?;
+            wait_frame(events, view, settings, times.flash)?;
+            view.flash = None;
+        }
+
+        if !settings.instant_settle {
+            settle(&mut game.grid);
+        }
+    }
+
+    // --spawn-delay holds the settled board before anything spawns
+    if !times.delay.is_zero() {
+        draw_frame(terminal, game, view, settings)?;
+        wait_frame(events, view, settings, times.delay)?;
+    }
+
+    // 3. SPAWN
+    // The new tile pops in at half size on the settled board, then run() draws it full size
+    // --trails shows the paths in the same frame, dimmed, so they cost no extra time
+    // --no-spawn-anim skips the frame (and its wait) entirely: the tile just appears with run()'s draw
+    if something_moved && !settings.spawn_anim {
+        game.last_spawn = game.spawn_tile();
+    } else if something_moved {
+        if settings.trails {
+            view.trail = visited.into_iter().filter(|&(r, c)| game.grid[r][c].is_none()).collect();
+            view.trail.sort_unstable();
+            view.trail.dedup();
+        }
+        game.last_spawn = game.spawn_tile();
+        view.pop_in = game.last_spawn.and_then(|(r, c, _)| game.grid[r][c]).map(|t| t.id);
+        show_frame(terminal, events, game, view, settings, times.spawn)?;
+        view.pop_in = None;
+        view.trail.clear();
+    }
+
+    debug_assert_eq!(game.validate(settings.score_rule, settings.merge_rule), Ok(()));
+    Ok(something_moved)
+}
+
+// --- PIPE MODE ---
+// --pipe plays over stdin/stdout instead of the terminal, one JSON object per line.
+//
+//   in:  a direction, bare or as JSON:   left    {"move":"left"}    (also "quit")
+//   out: the board after every move:     {"board":[[0,2,0,0],...],"score":4,"moves":1,"moved":true,"game_over":false}
+//        or, for a line that isn't a move: {"error":"unknown move 'jump'"}
+//
+// The first line out is the starting board, with "moved":false. Input ends at EOF or "quit".
+
+#[derive(Debug, PartialEq)]
+enum Launch {
+    Pipe,
+    Refuse,
+    Interactive,
+}
+
+// Raw mode and the alternate screen only make sense on a real terminal, so without --pipe a
+// redirected stdin or stdout is refused rather than garbled
+fn launch_mode(pipe: bool, stdin_tty: bool, stdout_tty: bool) -> Launch {
+    if pipe {
+        Launch::Pipe
+    } else if stdin_tty && stdout_tty {
+        Launch::Interactive
+    } else {
+        Launch::Refuse
+    }
+}
+
+enum PipeRequest {
+    Move(Direction),
+    Quit,
+}
+
+fn parse_pipe_request(line: &str) -> Result<PipeRequest, String> {
+    let line = line.trim();
+    let word = if line.starts_with('{') {
+        // Just enough JSON for {"move": "..."}
+        line.split_once("\"move\"")
+            .and_then(|(_, rest)| rest.trim_start().strip_prefix(':'))
+            .and_then(|rest| rest.trim_start().strip_prefix('"'))
+            .and_then(|rest| rest.split_once('"'))
+            .map(|(word, _)| word)
+            .ok_or_else(|| "expected {\"move\": \"up|down|left|right\"}".to_string())?
+    } else {
+        line
+    };
+    match word {
+        "up" => Ok(PipeRequest::Move(Direction::Up)),
+        "down" => Ok(PipeRequest::Move(Direction::Down)),
+        "left" => Ok(PipeRequest::Move(Direction::Left)),
+        "right" => Ok(PipeRequest::Move(Direction::Right)),
+        "quit" => Ok(PipeRequest::Quit),
+        other => Err(format!("unknown move '{}'", other)),
+    }
+}
+
+fn board_json(game: &Game, moved: bool) -> String {
+    let rows: Vec<String> = game
+        .grid
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row.iter().map(|cell| cell.map_or(0, |t| t.val).to_string()).collect();
+            format!("[{}]", cells.join(","))
+        })
+        .collect();
+    format!(
+        r#"{{"board":[{}],"score":{},"moves":{},"moved":{},"game_over":{}}}"#,
+        rows.join(","),
+        game.score,
+        game.moves,
+        moved,
+        game.game_over
+    )
+}
+
+// Same rules as the terminal game, minus the animation
+fn run_pipe<R: io::BufRead, W: Write>(
+    settings: &Settings,
+    input: R,
+    mut output: W,
+    sinks: &mut [Box<dyn EventSink>],
+) -> io::Result<()> {
+    let mut game = Game::new(settings.seed, settings.spawn_rules());
+    if let Some(board) = &settings.import {
+        game.load_board(board);
+    }
+    game.spawn_override = settings.force_spawn;
+    writeln!(output, "{}", board_json(&game, false))?;
+    output.flush()?;
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let dir = match parse_pipe_request(&line) {
+            Ok(PipeRequest::Move(dir)) => dir,
+            Ok(PipeRequest::Quit) => break,
+            Err(err) => {
+                writeln!(output, r#"{{"error":"{}"}}"#, err.replace('\\', "\\\\").replace('"', "\\\""))?;
+                output.flush()?;
+                continue;
+            }
+        };
+
+        if let Some(axis) = settings.chill
+            && !axis.dirs().contains(&dir)
+        {
+            writeln!(output, r#"{{"error":"{:?} is off under --chill"}}"#, dir)?;
+            output.flush()?;
+            continue;
+        }
+
+        let step = if game.game_over { None } else { replay_step(&mut game, dir, settings) };
+        let moved = step.is_some();
+        if let Some(step) = step {
+            emit_all(sinks, GameEvent::Move { dir, score: game.score });
+            for &(a, b) in &step.merged {
+                emit_all(sinks, GameEvent::Merge { value: a + b });
+            }
+            for (row, col, value) in step.spawns {
+                emit_all(sinks, GameEvent::Spawn { row, col, value });
+            }
+            if is_stuck(&game.grid, settings) {
+                game.game_over = true;
+                emit_all(sinks, GameEvent::GameOver { score: game.score, moves: game.moves });
+            }
+        }
+        writeln!(output, "{}", board_json(&game, moved))?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+// --- WATCH FILE ---
+// --watch-file PATH plays whatever moves another program writes to PATH, one character each
+// (w/k up, s/j down, a/h left, d/l right; anything else is ignored). The file is emptied once
+// read, so `printf ddw >> PATH` is all it takes. Until it exists there's simply nothing to play.
+
+fn watch_char(ch: char) -> Option<Direction> {
+    match ch.to_ascii_lowercase() {
+        'w' | 'k' => Some(Direction::Up),
+        's' | 'j' => Some(Direction::Down),
+        'a' | 'h' => Some(Direction::Left),
+        'd' | 'l' => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+fn consume_watch_file(path: &str) -> Vec<Direction> {
+    let Ok(text) = fs::read_to_string(path) else { return Vec::new() };
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let _ = fs::write(path, "");
+    text.chars().filter_map(watch_char).collect()
+}
+
+// --- DRAWING ---
+
+fn draw_ui<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    game: &Game,
+    view: &View,
+    settings: &Settings,
+) -> io::Result<()> {
+    let started = Instant::now();
+    terminal.draw(|f| {
+        let size = f.size();
+        
+        // Vertical Split; a hidden header hands its rows to the board
+        let header_h = if view.show_header { 3 } else { 0 };
+        let chunks = Layout::default()
+            .direction(Axis::Vertical)
+            .constraints([Constraint::Length(header_h), Constraint::Min(0)].as_ref())
+            .split(size);
+
+        // Header
+        if view.show_header {
+            f.render_widget(header_widget(game, view, settings), chunks[0]);
+        }
+
+        // --stream: the score in the block font above the board, the ticker below it
+        let chunks = if settings.stream {
+            let rows = Layout::default()
+                .direction(Axis::Vertical)
+                .constraints([Constraint::Length(STREAM_SCORE_HEIGHT), Constraint::Min(0), Constraint::Length(3)].as_ref())
+                .split(chunks[1]);
+            let score = Paragraph::new(render_block_label(&game.score.to_string()))
+                .alignment(Alignment::Center)
+                .block(Block::default().borders(Borders::ALL).title(" SCORE "));
+            f.render_widget(score, rows[0]);
+            let ticker = Paragraph::new(ticker_text(&game.milestones))
+                .block(Block::default().borders(Borders::ALL).title(" MILESTONES "));
+            f.render_widget(ticker, rows[2]);
+            [chunks[0], rows[1]]
+        } else {
+            [chunks[0], chunks[1]]
+        };
+
+        // Optional legend down the right-hand side, dropped when there's no room for it
+        let smallest_board = 4 * TILE_SIZES[TILE_SIZES.len() - 1].0;
+        let play_area = if settings.legend && chunks[1].width >= smallest_board + LEGEND_WIDTH {
+            let cols = Layout::default()
+                .direction(Axis::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(LEGEND_WIDTH)].as_ref())
+                .split(chunks[1]);
+            let legend = Paragraph::new(render_legend(settings))
+                .block(Block::default().borders(Borders::ALL).title(" TILES "));
+            f.render_widget(legend, cols[1]);
+            cols[0]
+        } else {
+            chunks[1]
+        };
+
+        // Pick the biggest tiles that fit, so narrow or short terminals still see the whole board
+        let (tile_w, tile_h) = fit_tile_size(play_area, 4, settings.tile_size);
+
+        // Centering Logic
+        let board_w = 4 * tile_w;
+        let board_h = 4 * tile_h;
+
+        let board_area = center_rect(play_area, board_w, board_h);
+
+        // Draw Background Board
+        let flash = view.flash.map(|val| flash_style(val, settings.theme));
+        let board_block = Block::default().borders(Borders::ALL).title(" RUST 2048 ")
+            .style(flash.unwrap_or_default());
+        f.render_widget(board_block, board_area);
+
+        // Draw Tiles
+        let coach_corner = max_corner(&game.grid);
+        for r in 0..4 {
+            for c in 0..4 {
+                let cell_area = Rect {
+                    x: board_area.x + (c as u16 * tile_w),
+                    y: board_area.y + (r as u16 * tile_h),
+                    width: tile_w,
+                    height: tile_h,
+                }
+                .intersection(size); // Even the smallest tiles can overflow a tiny terminal
+                if cell_area.is_empty() {
+                    continue;
+                }
+
+                let empty_cell_block = Block::default().borders(Borders::ALL)
+                    .style(flash.unwrap_or_else(|| empty_cell_style(settings.theme)));
+                let empty_cell = Paragraph::new("").block(empty_cell_block.clone());
+                let reverted = game.reverted.contains(&(r, c));
+
+                if let Some(tile) = game.grid[r][c] {
+                    let style = get_color_style(tile.val, settings.theme);
+                    let label = tile_label(tile.val, settings);
+
+                    // A tile that's just spawned starts at half size in the middle of its cell
+                    if view.pop_in == Some(tile.id) {
+                        f.render_widget(empty_cell, cell_area);
+                        let small = Rect {
+                            x: cell_area.x + cell_area.width / 4,
+                            y: cell_area.y + cell_area.height / 4,
+                            width: (cell_area.width / 2).max(1),
+                            height: (cell_area.height / 2).max(1),
+                        };
+                        let mut lines = vec![Line::from(""); (small.height / 2) as usize];
+                        lines.push(Line::from(label));
+                        f.render_widget(Paragraph::new(lines).alignment(Alignment::Center).style(style), small);
+                        continue;
+                    }
+
+                    let text_lines = if uses_block_text(tile.val, &label, tile_w, tile_h, settings) {
+                        // Taller --tile-height tiles keep the font in the middle
+                        let mut lines = vec![Line::from(""); ((tile_h - TILE_HEIGHT) / 2) as usize];
+                        lines.extend(render_block_text(tile.val, settings));
+                        lines
+                    } else {
+                        render_plain_text(label, tile_h)
+                    };
+                    
+                    // Tiles picked out by the merge preview get a double border, undone ones a thick one,
+                    // --coach rounds off the corner max and frozen tiles get a blocky one
+                    let previewed = view.preview.contains(&tile.id);
+                    let border = if previewed {
+                        BorderType::Double
+                    } else if reverted {
+                        BorderType::Thick
+                    } else if settings.coach && coach_corner == Some((r, c)) {
+                        BorderType::Rounded
+                    } else if tile.frozen > 0 {
+                        BorderType::QuadrantOutside
+                    } else {
+                        BorderType::Plain
+                    };
+
+                    // Half-block edges need a background colour to round off, so Mono keeps borders
+                    if settings.fancy_tiles && border == BorderType::Plain && let Some(bg) = style.bg {
+                        render_fancy_tile(f, cell_area, bg, Paragraph::new(text_lines).style(style));
+                        continue;
+                    }
+                    
+                    let p = Paragraph::new(text_lines)
+                        .alignment(Alignment::Center)
+                        .block(Block::default().borders(Borders::ALL).border_type(border))
+                        .style(style);
+                    f.render_widget(p, cell_area);
+                } else if reverted {
+                    f.render_widget(Paragraph::new("").block(empty_cell_block.border_type(BorderType::Thick)), cell_area);
+                } else if view.trail.contains(&(r, c)) {
+                    let fill = vec![Line::from("░".repeat(tile_w as usize)); tile_h as usize];
+                    f.render_widget(Paragraph::new(fill).block(empty_cell_block).style(trail_style(settings.theme)), cell_area);
+                } else if settings.checker && flash.is_none() {
+                    let tint = Block::default().borders(Borders::ALL).style(checker_style(r, c, settings.theme));
+                    f.render_widget(Paragraph::new("").block(tint), cell_area);
+                } else {
+                    f.render_widget(empty_cell, cell_area);
+                }
+            }
+        }
+
+        // The inspector's cursor goes over whatever the cell holds
+        if let Some((r, c)) = view.inspect {
+            let cursor_rect = Rect {
+                x: board_area.x + (c as u16 * tile_w),
+                y: board_area.y + (r as u16 * tile_h),
+                width: tile_w,
+                height: tile_h,
+            }
+            .intersection(size);
+            let cursor = Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .border_style(cursor_style(settings));
+            f.render_widget(cursor, cursor_rect);
+        }
+
+        if settings.minimap {
+            // Tucked into the top-right corner of the play area
+            let area = play_area;
+            let map_rect = Rect {
+                x: area.x + area.width.saturating_sub(6),
+                y: area.y,
+                width: 6,
+                height: 6,
+            }
+            .intersection(size);
+            let map = Paragraph::new(render_minimap(game, settings.theme))
+                .block(Block::default().borders(Borders::ALL));
+            f.render_widget(map, map_rect);
+        }
+
+        let overlay = if game.game_over {
+            // Only offer what the keys will actually do on this board
+            let replay = if game.replayable { "'r' replay, " } else { "" };
+            let practice = if settings.sandbox { "'p' practice, " } else { "" };
+            Some(format!(" GAME OVER - Enter: new game, {}{}'q' quit ", replay, practice))
+        } else {
+            view.banner.as_ref().map(|banner| banner.text.clone())
+                .or(game.paused_at.map(|_| " PAUSED ".to_string()))
+        };
+        if let Some(text) = overlay {
+            let width = text.chars().count() as u16 + 1;
+            let p = Paragraph::new(text)
+                .style(alert_style(settings.theme))
+                .alignment(Alignment::Center);
+            
+            let mid_rect = Rect {
+                x: (board_area.x + board_w/2).saturating_sub(width / 2),
+                y: board_area.y + board_h/2,
+                width,
+                height: 1
+            }
+            .intersection(size);
+            f.render_widget(p, mid_rect);
+        }
+
+        // Which way the game was played, under the game-over line
+        if game.game_over {
+            let mut lines = vec![Line::from(" move  played  merges ")];
+            for dir in Direction::ALL {
+                let (moves, merges) = (game.dir_moves[dir.index()], game.dir_merges[dir.index()]);
+                lines.push(Line::from(format!("  {}    {:>6}  {:>6} ", dir.arrow(), moves, merges)));
+            }
+            let (width, height) = (24, lines.len() as u16 + 2);
+            let table_rect = Rect {
+                x: (board_area.x + board_w / 2).saturating_sub(width / 2),
+                y: board_area.y + board_h / 2 + 2,
+                width,
+                height,
+            }
+            .intersection(size);
+            f.render_widget(Clear, table_rect);
+            f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL)), table_rect);
+        }
+
+        // --histogram: what the board ended up made of, above the game-over line
+        if game.game_over && settings.histogram {
+            let counts = tile_histogram(&game.grid);
+            let labels: Vec<String> = counts.iter().map(|&(val, _)| tile_label(val, settings)).collect();
+            let data: Vec<(&str, u64)> = labels.iter().map(String::as_str).zip(counts.iter().map(|&(_, n)| n)).collect();
+            let (width, height) = (counts.len() as u16 * 5 + 1, 9); // 4-wide bars, 1 apart, in a border
+            let chart_rect = Rect {
+                x: (board_area.x + board_w / 2).saturating_sub(width / 2),
+                y: (board_area.y + board_h / 2).saturating_sub(height + 1),
+                width,
+                height,
+            }
+            .intersection(size);
+            let chart = BarChart::default()
+                .block(Block::default().borders(Borders::ALL).title(" TILES "))
+                .bar_width(4)
+                .bar_gap(1)
+                .data(&data);
+            f.render_widget(Clear, chart_rect);
+            f.render_widget(chart, chart_rect);
+        }
+
+        if let Some(selected) = view.menu {
+            let lines = render_menu(selected, settings);
+            let (width, height) = (27, lines.len() as u16 + 2);
+            let menu_rect = Rect {
+                x: (board_area.x + board_w / 2).saturating_sub(width / 2),
+                y: (board_area.y + board_h / 2).saturating_sub(height / 2),
+                width,
+                height,
+            }
+            .intersection(size);
+            f.render_widget(Clear, menu_rect);
+            let menu = Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title(" SETTINGS (Esc) "));
+            f.render_widget(menu, menu_rect);
+        }
+
+        // --debug: the previous frame's numbers, so measuring never asks for a redraw of its own
+        if settings.debug && let Some(stats) = view.frame_stats.get() {
+            let fps = 1.0 / stats.interval.as_secs_f64().max(0.001);
+            let text = format!(" draw {:.1}ms  {:.0} fps ", stats.draw.as_secs_f64() * 1000.0, fps);
+            let corner = Rect { x: 0, y: size.height.saturating_sub(1), width: text.len() as u16, height: 1 }
+                .intersection(size);
+            f.render_widget(Paragraph::new(text).style(empty_cell_style(settings.theme)), corner);
+        }
+    })?;
+
+    let interval = view.frame_stats.get().map_or(Duration::ZERO, |last| started - last.started);
+    view.frame_stats.set(Some(FrameStats { started, draw: started.elapsed(), interval }));
+    Ok(())
+}
+
+// How many of each value are on the board, smallest value first
+fn tile_histogram(grid: &Grid) -> Vec<(u32, u64)> {
+    let mut counts: Vec<(u32, u64)> = Vec::new();
+    for tile in grid.iter().flatten().flatten() {
+        match counts.iter_mut().find(|(val, _)| *val == tile.val) {
+            Some(entry) => entry.1 += 1,
+            None => counts.push((tile.val, 1)),
+        }
+    }
+    counts.sort_unstable();
+    counts
+}
+
+// Solid tile with half-block top and bottom edges and the corners left out
+fn render_fancy_tile(f: &mut Frame, area: Rect, bg: Color, body: Paragraph) {
+    let inner = (area.width.saturating_sub(2)) as usize;
+    let edge = |ch: &str| Line::from(vec![Span::raw(" "), Span::styled(ch.repeat(inner), Style::default().fg(bg)), Span::raw(" ")]);
+    let top = Rect { height: 1, ..area };
+    let bottom = Rect { y: area.y + area.height.saturating_sub(1), height: 1, ..area };
+    let middle = Rect { y: area.y + 1, height: area.height.saturating_sub(2), ..area };
+
+    f.render_widget(Paragraph::new(edge("▄")), top);
+    f.render_widget(Paragraph::new(edge("▀")), bottom);
+    f.render_widget(body.alignment(Alignment::Center), middle);
+}
+
+fn draw_splash<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, settings: &Settings) -> io::Result<()> {
+    terminal.draw(|f| {
+        let size = f.size();
+
+        let mut lines = render_block_label("2048");
+        lines.push(Line::from(""));
+        lines.push(Line::from("R U S T   2 0 4 8"));
+        lines.push(Line::from(""));
+        lines.push(Line::from("press any key to start - 'q' to quit"));
+
+        let height = lines.len() as u16 + 2;
+        let area = Rect {
+            x: size.x,
+            y: size.y + size.height.saturating_sub(height) / 2,
+            width: size.width,
+            height: height.min(size.height),
+        };
+        let splash = Paragraph::new(lines)
+            .style(header_style(settings.theme))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(splash, area);
+    })?;
+    Ok(())
+}
+
+// Title screen before the first game; false means the player backed out
+fn run_splash<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    events: &Events,
+    settings: &Settings,
+) -> io::Result<bool> {
+    draw_splash(terminal, settings)?;
+    loop {
+        match events.read()? {
+            Event::Key(key) => {
+                return Ok(!(key_action(&key, settings) == Some(Action::Quit) || key.code == KeyCode::Esc));
+            }
+            Event::Resize(..) => draw_splash(terminal, settings)?,
+            _ => {}
+        }
+    }
+}
+
+fn key_action(key: &KeyEvent, settings: &Settings) -> Option<Action> {
+    // Raw mode swallows SIGINT, so Ctrl-C reaches us as a normal key press
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return Some(Action::Quit);
+    }
+    if key.code == KeyCode::Char('r') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return Some(Action::Redo);
+    }
+    if settings.merge_preview && let Some(dir) = preview_direction(key, &settings.keys) {
+        return Some(Action::Preview(dir));
+    }
+    settings.keys.get(&key.code).copied()
+}
+
+// Shift + any move key (an upper-case letter for letter keys) asks for a preview instead
+fn preview_direction(key: &KeyEvent, keys: &KeyMap) -> Option<Direction> {
+    let code = match key.code {
+        KeyCode::Char(ch) if ch.is_ascii_uppercase() => KeyCode::Char(ch.to_ascii_lowercase()),
+        code if key.modifiers.contains(KeyModifiers::SHIFT) => code,
+        _ => return None,
+    };
+    match keys.get(&code) {
+        Some(Action::Move(dir)) => Some(*dir),
+        _ => None,
+    }
+}
+
+// Marks the tiles that would merge if `dir` were played, without touching the board
+fn preview_move(game: &Game, view: &mut View, settings: &Settings, dir: Direction) {
+    let outcome = slide_and_merge(&game.grid, dir, settings.merge_rule);
+    view.preview = outcome.pairs.iter().flat_map(|&(a, b)| [a, b]).collect();
+    let text = if !outcome.moved {
+        "Can't move that way".to_string()
+    } else if outcome.pairs.is_empty() {
+        "No merges".to_string()
+    } else {
+        format!("+{} points", merge_points(&outcome.merged, settings.score_rule))
+    };
+    view.show_notice(&text, Duration::from_millis(1200));
+}
+
+// Drops a move that follows the last accepted one too closely
+fn should_accept(last: Option<Instant>, now: Instant, min_gap: Duration) -> bool {
+    last.is_none_or(|last| now.duration_since(last) >= min_gap)
+}
+
+// --celebrate MS: the board's background runs through every tile colour up to the winning one,
+// over and over, for the given time. Any key cuts it short.
+fn animate_win<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    events: &Events,
+    game: &mut Game,
+    view: &mut View,
+    settings: &Settings,
+) -> io::Result<()> {
+    let end = Instant::now() + settings.celebrate;
+    let colours = (1..=WIN_TILE.trailing_zeros()).map(|exp| 1 << exp).cycle();
+    for val in colours {
+        if Instant::now() >= end {
+            break;
+        }
+        view.flash = Some(val);
+        draw_frame(terminal, game, view, settings)?;
+        if events.input(Duration::from_millis(60))?.is_some() {
+            break;
+        }
+    }
+    view.flash = None;
+    if let Some(banner) = &mut view.banner {
+        banner.until = Instant::now() + Duration::from_secs(3); // Still readable once the show is over
+    }
+    Ok(())
+}
+
+// --shatter: the full board falls away a cell at a time, bottom row first, then comes back
+// for the game-over overlay. Any key skips the rest.
+fn animate_game_over<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    events: &Events,
+    game: &mut Game,
+    view: &View,
+    settings: &Settings,
+) -> io::Result<()> {
+    let board = game.grid;
+    game.game_over = false; // Hold the overlay back until the board is gone
+    for (r, c) in (0..4).rev().flat_map(|r| (0..4).map(move |c| (r, c))) {
+        game.grid[r][c] = None;
+        draw_frame(terminal, game, view, settings)?;
+        if events.input(Duration::from_millis(40))?.is_some() {
+            break;
+        }
+    }
+    game.grid = board;
+    game.game_over = true;
+    Ok(())
+}
+
+// The finished game again from its seed, a few seconds end to end; any key skips to the end.
+// It plays on a copy, so the real game's score and stats never see it.
+fn play_replay<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    events: &Events,
+    game: &Game,
+    settings: &Settings,
+) -> io::Result<()> {
+    let mut replay = Game::new(Some(game.seed), settings.spawn_rules());
+    let mut view = View::new();
+    let total = game.move_log.len();
+    let hold = (Duration::from_secs(3) / total.max(1) as u32).min(Duration::from_millis(60));
+
+    for (i, &dir) in game.move_log.iter().enumerate() {
+        replay_step(&mut replay, dir, settings);
+        let text = format!(" REPLAY  {}/{} ", i + 1, total);
+        view.banner = Some(Notice { text, until: Instant::now() + Duration::from_secs(60) });
+        draw_frame(terminal, &replay, &view, settings)?;
+        if events.input(hold)?.is_some() {
+            return Ok(());
+        }
+    }
+    debug_assert_eq!(replay.board_hash(), game.board_hash(), "replay drifted from the real game");
+    Ok(())
+}
+
+// One move without the animation, in the same order as commit_move: the move's own spawn, then the move
+// count, then any garbage. Nothing happens (and None comes back) if the move doesn't move.
+fn replay_step(replay: &mut Game, dir: Direction, settings: &Settings) -> Option<Step> {
+    let outcome = slide_and_merge(&replay.grid, dir, settings.merge_rule);
+    if !outcome.moved {
+        return None;
+    }
+    replay.grid = outcome.grid;
+    replay.score += merge_points(&outcome.merged, settings.score_rule);
+    let mut spawns: Vec<(usize, usize, u32)> = replay.spawn_tile().into_iter().collect();
+    replay.moves += 1;
+    if settings.garbage > 0 && replay.moves.is_multiple_of(settings.garbage) {
+        spawns.extend(replay.spawn_garbage());
+    }
+    Some(Step { merged: outcome.merged, spawns })
+}
+
+// What replay_step did, for anyone reporting it
+struct Step {
+    merged: Vec<(u32, u32)>,
+    spawns: Vec<(usize, usize, u32)>, // (row, col, value), the move's own spawn first
+}
+
+// For --idle-timeout: has the player walked away?
+fn idle_exceeded(last_input: Instant, now: Instant, timeout: Option<Duration>) -> bool {
+    timeout.is_some_and(|timeout| now.duration_since(last_input) >= timeout)
+}
+
+fn run<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    events: &Events,
+    settings: &Settings,
+    sinks: &mut [Box<dyn EventSink>],
+) -> io::Result<()> {
+    // The game (and its clock) only starts once the splash is dismissed
+    if settings.splash && !run_splash(terminal, events, settings)? {
+        return Ok(());
+    }
+    // The settings menu changes these as we go
+    let mut live = settings.clone();
+    let settings = &mut live;
+
+    let mut game = Game::new(settings.seed, settings.spawn_rules());
+    if let Some(board) = &settings.import {
+        game.load_board(board);
+    }
+    game.spawn_override = settings.force_spawn;
+    let mut view = View::new();
+    let mut stats = Stats::load();
+    draw_ui(terminal, &game, &view, settings)?;
+
+    let show_timer = !settings.minimal_header && settings.header.contains(&HeaderField::Time);
+    let mut last_move_at: Option<Instant> = None;
+    let mut last_input = Instant::now();
+
+    loop {
+        if view.quit_requested {
+            break;
+        }
+
+        // Moves typed during the last animation go first, oldest first
+        let action = if let Some(dir) = view.queued_moves.pop_front() {
+            Some(Action::Move(dir))
+        } else {
+            let event = match events.next()? {
+                Incoming::Input(event) => event,
+                Incoming::Failed(err) => return Err(err),
+                Incoming::Tick => {
+                    // A tick: leave the way 'q' would if nothing has been pressed for too long
+                    if idle_exceeded(last_input, Instant::now(), settings.idle_timeout) {
+                        break;
+                    }
+                    if let Some(path) = &settings.watch_file {
+                        let dirs = consume_watch_file(path);
+                        if !dirs.is_empty() {
+                            view.queued_moves.extend(dirs);
+                            last_input = Instant::now();
+                            continue;
+                        }
+                    }
+                    // Otherwise keep the clock ticking if it's on screen
+                    if view.expire_notice() || show_timer {
+                        draw_ui(terminal, &game, &view, settings)?;
+                    }
+                    continue;
+                }
+            };
+            last_input = Instant::now();
+            match event {
+                Event::Key(key) => {
+                    // In case the terminal reports losing focus but never getting it back
+                    game.resume();
+                    let action = key_action(&key, settings);
+                    if view.menu.is_some() && action != Some(Action::Quit) {
+                        menu_key(&mut view, settings, key.code);
+                        draw_ui(terminal, &game, &view, settings)?;
+                        continue;
+                    }
+                    if view.inspect.is_some() && action != Some(Action::Quit) {
+                        inspect_key(&mut game, &mut view, settings, key.code, action);
+                        draw_ui(terminal, &game, &view, settings)?;
+                        continue;
+                    }
+                    action
+                }
+                Event::FocusLost if settings.pause_on_blur => {
+                    game.pause();
+                    draw_ui(terminal, &game, &view, settings)?;
+                    continue;
+                }
+                Event::FocusGained => {
+                    game.resume();
+                    draw_ui(terminal, &game, &view, settings)?;
+                    continue;
+                }
+                _ => continue,
+            }
+        };
+
+        // A preview only lasts until the next key
+        if !view.preview.is_empty() {
+            view.preview.clear();
+            draw_ui(terminal, &game, &view, settings)?;
+        }
+
+        let Some(action) = action else { continue };
+
+        match action {
+            Action::Quit => break,
+            Action::ResetScore if settings.debug => {
+                game.score = 0;
+                game.score_edited = true;
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::ForceSpawn if settings.debug && settings.force_spawn.is_some() => {
+                game.spawn_override = settings.force_spawn;
+                view.show_notice("Next spawn is forced", Duration::from_secs(2));
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::Inspect => {
+                view.inspect = Some((0, 0));
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            // Picking the tile to freeze borrows the inspector's cursor, starting on the max tile
+            Action::Freeze if settings.powerups && !game.game_over => {
+                if game.freezes_left == 0 {
+                    view.show_notice("No freezes left", Duration::from_secs(2));
+                } else {
+                    view.inspect = Some(max_corner(&game.grid).unwrap_or((0, 0)));
+                    view.show_notice("Pick a tile to freeze, then 'f' again", Duration::from_secs(3));
+                }
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            // The score goes with the header, so it shows over the board for a moment instead
+            Action::Header => {
+                view.show_header = !view.show_header;
+                if !view.show_header {
+                    let text = format!(" SCORE: {} ", game.score);
+                    view.banner = Some(Notice { text, until: Instant::now() + Duration::from_millis(1500) });
+                }
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::Menu => {
+                view.menu = Some(0);
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::Export => {
+                let text = match export_board(&board_text(&game.grid)) {
+                    Ok(None) => "Board copied".to_string(),
+                    Ok(Some(path)) => format!("No clipboard; board saved to {}", path),
+                    Err(err) => format!("Couldn't export the board: {}", err),
+                };
+                view.show_notice(&text, Duration::from_secs(3));
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::Restart if game.game_over => {
+                // A fresh board each time; replaying a --seed is what --challenge is for
+                game = Game::new(None, settings.spawn_rules());
+                view = View { show_header: view.show_header, ..View::new() };
+                last_move_at = None;
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::Replay if game.game_over && game.replayable => {
+                play_replay(terminal, events, &game, settings)?;
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::Practice if game.game_over && settings.sandbox => {
+                game.enter_sandbox();
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::Undo if game.sandbox.is_some() && game.undo() => {
+                if settings.undo_highlight {
+                    show_frame(terminal, events, &mut game, &mut view, settings, Duration::from_millis(150))?;
+                }
+                game.reverted.clear();
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::Redo if game.sandbox.is_some() && game.redo() => {
+                if settings.undo_highlight {
+                    show_frame(terminal, events, &mut game, &mut view, settings, Duration::from_millis(150))?;
+                }
+                game.reverted.clear();
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            _ if game.game_over => {}
+            Action::Preview(dir) => {
+                preview_move(&game, &mut view, settings, dir);
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::Scramble if settings.scramble => {
+                game.scramble();
+                draw_ui(terminal, &game, &view, settings)?;
+            }
+            Action::Move(dir) => {
+                let now = Instant::now();
+                if !should_accept(last_move_at, now, settings.debounce) {
+                    continue;
+                }
+
+                // A warning costs one key press; the same key again means "I know"
+                let warning = if view.warned_move == Some(dir) {
+                    None
+                } else if settings.careful && ends_game(&game, settings, dir) {
+                    Some("This move will end the game - press again to confirm")
+                } else if settings.coach && displaces_corner_max(&game.grid, dir, settings.merge_rule) {
+                    Some("That pulls your biggest tile out of its corner")
+                } else {
+                    None
+                };
+                if let Some(text) = warning {
+                    view.warned_move = Some(dir);
+                    view.show_notice(text, Duration::from_secs(3));
+                    draw_ui(terminal, &game, &view, settings)?;
+                    continue;
+                }
+                view.warned_move = None;
+
+                // Only a move that happened starts the gap: a bump into a wall or a warning shouldn't
+                // swallow the next key
+                if commit_move(terminal, events, &mut game, &mut view, settings, &mut stats, sinks, dir)? {
+                    last_move_at = Some(now);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if settings.save_settings {
+        save_settings(settings)?;
+    }
+    Ok(())
+}
+
+// One move, start to finish: animate it, which spawns exactly one tile if (and only if) the
+// board changed, then do the bookkeeping, redraw and check for the end. Returns whether it moved.
+#[allow(clippy::too_many_arguments)]
+fn commit_move<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    events: &Events,
+    game: &mut Game,
+    view: &mut View,
+    settings: &Settings,
+    stats: &mut Stats,
+    sinks: &mut [Box<dyn EventSink>],
+    dir: Direction,
+) -> io::Result<bool> {
+    if let Some(axis) = settings.chill
+        && !axis.dirs().contains(&dir)
+    {
+        let [a, b] = axis.dirs();
+        view.show_notice(&format!("Only {} and {} this game", a.arrow(), b.arrow()), Duration::from_secs(2));
+        draw_ui(terminal, game, view, settings)?;
+        return Ok(false);
+    }
+    if game.sandbox.is_some() {
+        game.push_history();
+    }
+    // Only worth the solver's time when it's being tracked, and never in the sandbox
+    let advice = if settings.track_optimal && game.sandbox.is_none() {
+        best_move(&game.grid, settings)
+    } else {
+        None
+    };
+    let tiles_before = game.tile_count();
+    let moved = animate_move(terminal, events, game, view, settings, dir)?;
+    // Each merge takes a tile away and a move that did anything adds one, a no-op adds none
+    debug_assert_eq!(game.tile_count(), tiles_before - game.last_merged.len() + usize::from(moved));
+    if settings.debug_anim {
+        step_frames(terminal, events, game, view, settings)?;
+    }
+    if moved && let Some(advice) = advice {
+        game.ai_checked += 1;
+        if advice == dir {
+            game.ai_matched += 1;
+        }
+    }
+    if !moved && game.sandbox.is_some() {
+        game.history.pop();
+    }
+    if moved {
+        game.redo_stack.clear(); // A new line of play; the undone one is gone
+    }
+
+    if moved {
+        game.moves += 1;
+        game.last_dir = Some(dir);
+        thaw_tiles(&mut game.grid);
+        game.note_milestone();
+        if game.sandbox.is_none() {
+            game.move_log.push(dir);
+            game.dir_moves[dir.index()] += 1;
+            game.dir_merges[dir.index()] += game.last_merged.len() as u32;
+        }
+        emit_all(sinks, GameEvent::Move { dir, score: game.score });
+        for &value in &game.last_merged {
+            emit_all(sinks, GameEvent::Merge { value });
+        }
+        if settings.combo_notices {
+            announce_combo(game, view);
+        }
+        if !game.won
+            && game.sandbox.is_none()
+            && game.last_merged.iter().any(|&v| v >= WIN_TILE)
+        {
+            game.won = true;
+            stats.record_win();
+            if !settings.debug {
+                stats.save();
+            }
+            let text = format!(" YOU WIN!  Win streak: {} ", stats.win_streak);
+            view.banner = Some(Notice { text, until: Instant::now() + Duration::from_secs(3) });
+            if !settings.celebrate.is_zero() && !settings.reduce_motion {
+                animate_win(terminal, events, game, view, settings)?;
+            }
+        }
+        if let Some((row, col, value)) = game.last_spawn.take() {
+            emit_all(sinks, GameEvent::Spawn { row, col, value });
+        }
+        if settings.garbage > 0
+            && game.moves.is_multiple_of(settings.garbage)
+            && let Some((row, col, value)) = game.spawn_garbage()
+        {
+            emit_all(sinks, GameEvent::Spawn { row, col, value });
+        }
+        // Same rule as the scores: debug boards and the sandbox don't count
+        if game.sandbox.is_none() && !settings.debug {
+            let unlocked = stats.achievements.check(game);
+            if let Some(last) = unlocked.last() {
+                stats.achievements.save();
+                view.show_notice(&format!("Achievement unlocked: {}", last.title()), Duration::from_secs(3));
+            }
+        }
+        draw_ui(terminal, game, view, settings)?;
+
+        // Simple Game Over Check (the sandbox never ends by itself)
+        let stuck = game.sandbox.is_none() && is_stuck(&game.grid, settings);
+        if stuck {
+             game.game_over = true;
+             stats.record_game_over(game.score, game.won);
+             if !settings.debug {
+                 stats.save(); // Debug sessions never touch the real scores
+                 record_summary(&GameSummary::new(game, settings));
+             }
+             emit_all(sinks, GameEvent::GameOver { score: game.score, moves: game.moves });
+             if settings.shatter && !settings.reduce_motion {
+                 animate_game_over(terminal, events, game, view, settings)?;
+             }
+             draw_ui(terminal, game, view, settings)?;
+        }
+    }
+    Ok(moved)
+}
+
+fn announce_combo(game: &Game, view: &mut View) {
+    let text = match game.last_merged.len() {
+        0 | 1 => return,
+        2 => "Double merge!",
+        3 => "Triple merge!",
+        _ => "Mega merge!",
+    };
+    view.show_notice(text, Duration::from_millis(1200));
+}
+
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, DisableFocusChange, cursor::Show)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let parsed = load_config(flag_value(&args, "--config")).and_then(|config| Settings::from_args(args, &config));
+    let mut settings = match parsed {
+        Ok(settings) => settings,
+        Err(msg) => {
+            eprintln!("rust_2048: {}", msg);
+            process::exit(2);
+        }
+    };
+    let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let no_color = no_color || settings.no_color;
+    settings.requested_theme = settings.theme;
+    settings.theme = resolve_theme(settings.theme, no_color, settings.force_color, settings.color_depth());
+
+    // Opened before raw mode so a warning is still readable
+    if let Some(path) = &settings.log_file
+        && let Err(err) = init_logger(path, settings.log_level)
+    {
+        eprintln!("rust_2048: not logging, can't open '{}': {}", path, err);
+    }
+    let mut sinks: Vec<Box<dyn EventSink>> = Vec::new();
+    if let Some(path) = &settings.event_log {
+        match FileEventSink::open(path) {
+            Ok(sink) => sinks.push(Box::new(sink)),
+            Err(err) => eprintln!("rust_2048: not logging events, can't open '{}': {}", path, err),
+        }
+    }
+    if let Some(command) = &settings.on_merge_cmd {
+        sinks.push(Box::new(CommandEventSink::new(command)));
+    }
+
+    if settings.verify_history {
+        verify_history();
+        return Ok(());
+    }
+
+    match launch_mode(settings.pipe, io::stdin().is_terminal(), io::stdout().is_terminal()) {
+        Launch::Pipe => {
+            run_pipe(&settings, io::stdin().lock(), io::stdout().lock(), &mut sinks)?;
+            return Ok(());
+        }
+        Launch::Refuse => {
+            eprintln!("rust_2048: requires an interactive terminal (stdin or stdout is redirected); use --pipe for scripted mode");
+            process::exit(2);
+        }
+        Launch::Interactive => {}
+    }
+
+    // Put the terminal back before a panic message gets printed into the alternate screen
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // Terminals without focus reporting just never send the events, so the game never pauses
+    if settings.pause_on_blur {
+        execute!(stdout, EnableFocusChange)?;
+    }
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    // Quitting and errors both come back through here so the terminal is always restored,
+    // once the event threads are stopped and joined
+    let events = Events::start();
+    let result = run(&mut terminal, &events, &settings, &mut sinks);
+    drop(events);
+    restore_terminal()?;
+    result?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, buffer::Buffer};
+    use std::{cell::RefCell, rc::Rc};
+
+    // A seeded game with the starting tiles cleared, so tests place exactly what they need
+    fn empty_game() -> Game {
+        let mut game = Game::new(Some(0), SpawnRules::default());
+        game.grid = [[None; 4]; 4];
+        game
+    }
+
+    fn render(game: &Game, settings: &Settings, width: u16, height: u16) -> Buffer {
+        render_view(game, &View::new(), settings, width, height)
+    }
+
+    fn render_view(game: &Game, view: &View, settings: &Settings, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        draw_ui(&mut terminal, game, view, settings).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    fn text_at(buf: &Buffer, x: u16, y: u16, len: u16) -> String {
+        (x..x + len).map(|x| buf.get(x, y).symbol()).collect()
+    }
+
+    #[test]
+    fn header_shows_score_centred() {
+        let mut game = empty_game();
+        game.score = 1234;
+        let buf = render(&game, &Settings::default(), 80, 40);
+
+        // " SCORE: 1234 " is 13 wide, centred in the 78 columns inside the border
+        // (ratatui puts the odd leftover column on the left)
+        assert_eq!(text_at(&buf, 1 + 33, 1, 13), " SCORE: 1234 ");
+        assert_eq!(text_at(&buf, 0, 0, 1), "┌");
+        assert_eq!(text_at(&buf, 79, 2, 1), "┘");
+    }
+
+    #[test]
+    fn tile_draws_block_digits_in_its_cell() {
+        let mut game = empty_game();
+        game.grid[0][0] = Some(Tile { val: 2, id: 0, frozen: 0 });
+        let buf = render(&game, &Settings::default(), 80, 40);
+
+        // 80x40 fits full-size tiles, centred in the 80x37 below the header: (4, 7)..(22, 14)
+        assert_eq!(text_at(&buf, 4, 7, 1), "┌");
+        assert_eq!(text_at(&buf, 21, 13, 1), "┘");
+
+        // The "2" glyph is centred in the 16 columns inside the tile border
+        for (row, expected) in FONT[2].iter().enumerate() {
+            assert_eq!(text_at(&buf, 11, 8 + row as u16, 3), *expected);
+        }
+        assert_eq!(buf.get(11, 8).style().bg, Some(Color::White));
+    }
+
+    // Golden frames live in src/snapshots/<name>.txt as plain text (symbols only, no colours),
+    // one line per row with trailing spaces trimmed. After a deliberate rendering change,
+    // regenerate them with `UPDATE_SNAPSHOTS=1 cargo test` and review the diff before committing.
+    fn assert_snapshot(name: &str, buf: &Buffer) {
+        let area = buf.area;
+        let actual: String = (0..area.height)
+            .map(|y| text_at(buf, 0, y, area.width).trim_end().to_string() + "\n")
+            .collect();
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src").join("snapshots").join(format!("{}.txt", name));
+
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("no snapshot at {}; run with UPDATE_SNAPSHOTS=1 to create it", path.display()));
+        assert!(actual == expected, "{} doesn't match its snapshot:\n{}", name, actual);
+    }
+
+    #[test]
+    fn snapshot_initial_board() {
+        let game = Game::new(Some(0), SpawnRules::default());
+        assert_snapshot("initial_board", &render(&game, &Settings::default(), 80, 40));
+    }
+
+    #[test]
+    fn snapshot_mid_game() {
+        let mut game = empty_game();
+        for (i, &(r, c, val)) in [(0, 0, 2), (0, 1, 4), (1, 1, 8), (2, 3, 16), (3, 0, 128), (3, 3, 2048)].iter().enumerate() {
+            game.grid[r][c] = Some(Tile { val, id: i, frozen: 0 });
+        }
+        game.score = 5000;
+        assert_snapshot("mid_game", &render(&game, &Settings::default(), 80, 40));
+    }
+
+    #[test]
+    fn snapshot_game_over() {
+        let mut game = empty_game();
+        for (i, cell) in game.grid.iter_mut().flatten().enumerate() {
+            *cell = Some(Tile { val: 2 << (i % 4 + i / 4 % 2), id: i, frozen: 0 });
+        }
+        game.game_over = true;
+        (game.dir_moves, game.dir_merges) = ([12, 3, 20, 18], [4, 1, 9, 7]);
+        assert_snapshot("game_over", &render(&game, &Settings::default(), 80, 40));
+    }
+
+    fn args(list: &[&str]) -> Result<Settings, String> {
+        Settings::from_args(list.iter().map(|arg| arg.to_string()).collect(), &Config::default())
+    }
+
+    #[test]
+    fn tile_size_flags_reject_too_small_and_too_large() {
+        assert!(args(&["--tile-width", "17"]).is_err());
+        assert!(args(&["--tile-height", "6"]).is_err());
+        assert!(args(&["--tile-width", "20000"]).is_err());
+        assert!(args(&["--tile-height", "201"]).is_err());
+        assert_eq!(args(&["--tile-width", "24", "--tile-height", "9"]).unwrap().tile_size, (24, 9));
+    }
+
+    #[test]
+    fn largest_tile_size_still_draws() {
+        let game = empty_game();
+        let settings = args(&["--tile-width", "200", "--tile-height", "200"]).unwrap();
+        render(&game, &settings, 80, 40); // No overflow, falls back to a size that fits
+    }
+
+    fn grid_of(values: [[u32; 4]; 4]) -> Grid {
+        let mut grid = [[None; 4]; 4];
+        for (r, row) in values.iter().enumerate() {
+            for (c, &val) in row.iter().enumerate() {
+                grid[r][c] = (val != 0).then_some(Tile { val, id: r * 4 + c, frozen: 0 });
+            }
+        }
+        grid
+    }
+
+    fn values_of(grid: &Grid) -> [[u32; 4]; 4] {
+        grid.map(|row| row.map(|cell| cell.map_or(0, |t| t.val)))
+    }
+
+    #[test]
+    fn custom_merge_rule_uses_its_predicate() {
+        fn two_and_four(a: u32, b: u32) -> Option<u32> {
+            (a.min(b) == 2 && a.max(b) == 4).then_some(a + b)
+        }
+        let rule = MergeRule::Custom(two_and_four);
+        let grid = grid_of([[2, 4, 0, 0], [2, 8, 0, 0], [0; 4], [0; 4]]);
+
+        let pass = merge_step(&grid, 0, -1, rule);
+        assert_eq!(pass.merged, vec![(4, 2)]); // (moving, absorbed): the 4 slides into the 2
+        let outcome = slide_and_merge(&grid, Direction::Left, rule);
+        assert_eq!(values_of(&outcome.grid), [[6, 0, 0, 0], [2, 8, 0, 0], [0; 4], [0; 4]]);
+    }
+
+    fn pipe_lines(settings: &Settings, input: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        run_pipe(settings, io::Cursor::new(input), &mut output, &mut []).unwrap();
+        String::from_utf8(output).unwrap().lines().map(str::to_string).collect()
+    }
+
+    fn tiles_in(line: &str) -> usize {
+        parse_import(line).unwrap().values.iter().flatten().filter(|&&v| v != 0).count()
+    }
+
+    #[test]
+    fn pipe_answers_every_line() {
+        let settings = Settings { seed: Some(1), ..Settings::default() };
+        let lines = pipe_lines(&settings, "left\n\n{\"move\":\"right\"}\njump\nquit\nup\n");
+
+        // Starting board, two moves, the error; the blank line is skipped and nothing runs after quit
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains(r#""moves":0,"moved":false"#));
+        assert_eq!(tiles_in(&lines[0]), 2);
+        assert!(lines[1].starts_with(r#"{"board":"#));
+        assert!(lines[2].contains(r#""game_over":false"#));
+        assert_eq!(lines[3], r#"{"error":"unknown move 'jump'"}"#);
+    }
+
+    #[test]
+    fn pipe_drops_garbage_like_the_terminal_game() {
+        let plain = Settings { seed: Some(1), ..Settings::default() };
+        let garbage = Settings { garbage: 1, ..plain.clone() };
+        let (plain, garbage) = (pipe_lines(&plain, "left\nright\n"), pipe_lines(&garbage, "left\nright\n"));
+        // Same seed, so the same first spawn; --garbage 1 adds a 2 on top of it every move
+        assert_eq!(tiles_in(&garbage[1]), tiles_in(&plain[1]) + 1);
+        assert!(tiles_in(&garbage[2]) > tiles_in(&garbage[1]));
+    }
+
+    #[test]
+    fn save_settings_keeps_the_requested_theme() {
+        let path = env::temp_dir().join(format!("rust2048-save-{}.conf", process::id()));
+        let settings = Settings {
+            theme: Theme::Mono, // What NO_COLOR forced
+            requested_theme: Theme::Classic,
+            config: Some(path.display().to_string()),
+            ..Settings::default()
+        };
+        save_settings(&settings).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(text.contains("theme = classic\n"), "{}", text);
+    }
+
+    // Plays one move through commit_move on a TestBackend; --debug keeps the real scores out of it
+    fn commit(game: &mut Game, dir: Direction) -> bool {
+        let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+        let settings = Settings { reduce_motion: true, debug: true, ..Settings::default() };
+        commit_move(&mut terminal, &Events::headless(), game, &mut View::new(), &settings, &mut Stats::default(), &mut [], dir).unwrap()
+    }
+
+    #[test]
+    fn only_a_move_that_moves_spawns() {
+        let mut game = empty_game();
+        game.grid = grid_of([[2, 4, 0, 0], [0; 4], [0; 4], [0; 4]]);
+
+        assert!(!commit(&mut game, Direction::Left)); // Already against the wall
+        assert_eq!((game.tile_count(), game.moves), (2, 0));
+
+        assert!(commit(&mut game, Direction::Right));
+        assert_eq!((game.tile_count(), game.moves), (3, 1));
+    }
+
+    #[test]
+    fn spawn_cell_is_fixed_by_seed_and_board() {
+        let mut game = empty_game();
+        game.grid = grid_of([[2, 0, 4, 0], [0, 8, 0, 0], [0; 4], [16, 0, 0, 2]]);
+        game.rng = StdRng::seed_from_u64(42);
+        // Eleven free cells; seed 42 rolls index 7, which is (2, 2) in row-major order
+        let (r, c, _) = game.spawn_tile().unwrap();
+        assert_eq!((r, c), (2, 2));
+    }
+
+    #[test]
+    fn validate_catches_broken_boards() {
+        let check = |game: &Game| game.validate(ScoreRule::Standard, MergeRule::Doubling);
+        let mut game = empty_game();
+        game.grid = grid_of([[2, 4, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        assert_eq!(check(&game), Ok(()));
+
+        game.grid[3][3] = game.grid[0][0];
+        assert_eq!(check(&game), Err("tile id 0 appears twice".to_string()));
+
+        game.grid = grid_of([[2, 6, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        assert_eq!(check(&game), Err("tile 1 has value 6".to_string()));
+
+        // Building a 16 from 4s scores at least 8 + 8 + 16
+        game.grid = grid_of([[16, 0, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        game.score = 31;
+        assert_eq!(check(&game), Err("score 31 is below the 32 the board's tiles account for".to_string()));
+        game.score = 32;
+        assert_eq!(check(&game), Ok(()));
+    }
+
+    #[test]
+    fn board_hash_sees_values_not_ids() {
+        let mut a = empty_game();
+        a.grid = grid_of([[2, 4, 0, 0], [0, 8, 0, 0], [0; 4], [0, 0, 0, 2]]);
+        let mut b = empty_game();
+        b.grid = a.grid.map(|row| row.map(|cell| cell.map(|t| Tile { id: t.id + 100, ..t })));
+        assert_eq!(a.board_hash(), b.board_hash());
+
+        b.grid = grid_of([[4, 2, 0, 0], [0, 8, 0, 0], [0; 4], [0, 0, 0, 2]]);
+        assert_ne!(a.board_hash(), b.board_hash());
+        b.grid = grid_of([[2, 4, 0, 0], [0, 8, 0, 0], [0; 4], [0, 0, 2, 0]]);
+        assert_ne!(a.board_hash(), b.board_hash());
+    }
+
+    #[test]
+    fn verify_entry_rejects_an_edited_score() {
+        let settings = Settings::default();
+        let mut game = Game::new(Some(9), settings.spawn_rules());
+        for dir in [Direction::Left, Direction::Up, Direction::Right, Direction::Down, Direction::Left] {
+            if replay_step(&mut game, dir, &settings).is_some() {
+                game.move_log.push(dir);
+            }
+        }
+        let mut entry = GameSummary::new(&game, &settings);
+        assert!(entry.log.is_some());
+        assert!(verify_entry(&entry));
+
+        entry.score += 4;
+        assert!(!verify_entry(&entry));
+        // Changing the log too doesn't help without the checksum to match
+        entry.score -= 4;
+        entry.log = Some("LLLL".to_string());
+        assert!(!verify_entry(&entry));
+    }
+
+    #[test]
+    fn chill_ignores_the_other_axis() {
+        let mut game = empty_game();
+        // Every row is packed with no pairs, so the only merges are up and down
+        game.grid = grid_of([[2, 4, 2, 4], [2, 4, 2, 4], [0; 4], [0; 4]]);
+        let free = Settings::default();
+        let chill = Settings { chill: Some(MoveAxis::Horizontal), ..Settings::default() };
+
+        assert!(!is_stuck(&game.grid, &free));
+        assert!(is_stuck(&game.grid, &chill));
+        assert_eq!(best_move(&game.grid, &free), Some(Direction::Up));
+        assert_eq!(best_move(&game.grid, &chill), None);
+        assert!(board_pressure(&game.grid, &chill) > board_pressure(&game.grid, &free));
+
+        // Up would fill the board, but under --chill it isn't a move at all
+        game.grid = grid_of([[2, 4, 2, 4], [8, 16, 8, 16], [2, 4, 2, 0], [4, 2, 4, 4]]);
+        assert!(ends_game(&game, &free, Direction::Up));
+        assert!(!ends_game(&game, &chill, Direction::Up));
+    }
+
+    #[test]
+    fn game_over_offers_replay_only_when_it_can() {
+        let screen = |game: &Game| {
+            let buf = render(game, &Settings::default(), 80, 40);
+            (0..40).map(|y| text_at(&buf, 0, y, 80)).collect::<Vec<_>>().join("\n")
+        };
+        let mut game = empty_game();
+        game.game_over = true;
+        assert!(screen(&game).contains("Enter: new game, 'r' replay, 'q' quit"));
+
+        game.replayable = false;
+        assert!(screen(&game).contains("Enter: new game, 'q' quit"));
+        assert!(!screen(&game).contains("replay"));
+    }
+
+    #[test]
+    fn debounce_drops_only_moves_inside_the_gap() {
+        let gap = Duration::from_millis(100);
+        let start = Instant::now();
+        assert!(should_accept(None, start, gap));
+        assert!(!should_accept(Some(start), start + Duration::from_millis(99), gap));
+        assert!(should_accept(Some(start), start + gap, gap));
+        assert!(should_accept(Some(start), start, Duration::ZERO));
+    }
+
+    #[test]
+    fn cursor_color_comes_from_flag_or_config() {
+        assert_eq!(args(&["--cursor-color", "yellow"]).unwrap().cursor_color, Some(Color::Yellow));
+        assert!(args(&["--cursor-color", "glitter"]).is_err());
+        let config = Config::parse("[settings]\ncursor_color = #ff8800\n").unwrap();
+        let settings = Settings::from_args(Vec::new(), &config).unwrap();
+        assert_eq!(settings.cursor_color, Some(Color::Rgb(0xff, 0x88, 0x00)));
+
+        let mut game = empty_game();
+        game.grid = grid_of([[2, 0, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        let view = View { inspect: Some((0, 0)), ..View::new() };
+        let corner_colors = |settings: &Settings| {
+            let buf = render_view(&game, &view, settings, 80, 40);
+            buf.content.iter().filter(|cell| cell.symbol() == "┏").map(|cell| (cell.fg, cell.bg)).collect::<Vec<_>>()
+        };
+        let yellow = Settings { cursor_color: Some(Color::Yellow), ..Settings::default() };
+        assert_eq!(corner_colors(&yellow), vec![(Color::Yellow, Color::Black)]);
+        // Mono ignores it: no colours there at all
+        let mono = Settings { theme: Theme::Mono, ..yellow };
+        assert_eq!(corner_colors(&mono), vec![(Color::Reset, Color::Reset)]);
+    }
+
+    #[test]
+    fn hidden_header_leaves_only_the_board() {
+        let game = empty_game();
+        let screen = |view: &View| {
+            let buf = render_view(&game, view, &Settings::default(), 80, 40);
+            (0..40).map(|y| text_at(&buf, 0, y, 80)).collect::<Vec<_>>().join("\n")
+        };
+        assert!(screen(&View::new()).contains("SCORE: 0"));
+        assert!(!screen(&View { show_header: false, ..View::new() }).contains("SCORE"));
+    }
+
+    #[test]
+    fn each_score_rule_scores_a_512() {
+        assert_eq!(score_for_merge(256, 256, ScoreRule::Standard), 512);
+        assert_eq!(score_for_merge(256, 256, ScoreRule::Log), 9);
+        assert_eq!(score_for_merge(256, 256, ScoreRule::Flat), 1);
+    }
+
+    #[test]
+    fn fibonacci_merges_only_neighbours() {
+        let merge = |a, b| can_merge(a, b, MergeRule::Fibonacci);
+        assert_eq!(merge(1, 1), Some(2));
+        assert_eq!(merge(1, 2), Some(3));
+        assert_eq!(merge(2, 1), Some(3));
+        assert_eq!(merge(2, 3), Some(5));
+        assert_eq!(merge(1, 3), None);
+        assert_eq!(merge(2, 5), None);
+    }
+
+    #[test]
+    fn challenge_codes_round_trip() {
+        for seed in [0, 1, 61, 62, 12345, u64::MAX] {
+            for score_rule in [ScoreRule::Standard, ScoreRule::Log, ScoreRule::Flat] {
+                for (merge_rule, no_adjacent_spawn) in [(MergeRule::Doubling, false), (MergeRule::Fibonacci, true)] {
+                    let settings = Settings { seed: Some(seed), score_rule, merge_rule, no_adjacent_spawn, ..Settings::default() };
+                    let decoded = decode_challenge(&encode_challenge(&settings)).unwrap();
+                    assert_eq!(decoded.seed, Some(seed));
+                    assert_eq!(decoded.score_rule, score_rule);
+                    assert_eq!(matches!(decoded.merge_rule, MergeRule::Fibonacci), matches!(merge_rule, MergeRule::Fibonacci));
+                    assert_eq!(decoded.no_adjacent_spawn, no_adjacent_spawn);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn challenge_codes_reject_tampering() {
+        // Version 1 of a raw number, to build codes encode_challenge never would
+        let code_for = |mut n: u128| {
+            let mut digits = Vec::new();
+            while n > 0 {
+                digits.push(BASE62[(n % 62) as usize] as char);
+                n /= 62;
+            }
+            std::iter::once('1').chain(digits.into_iter().rev()).collect::<String>()
+        };
+        assert!(decode_challenge(&code_for(7 << 16 | 4 << 8)).is_ok());
+
+        let code = encode_challenge(&Settings { seed: Some(7), ..Settings::default() });
+        assert!(decode_challenge(&code.replacen('1', "2", 1)).is_err()); // Unknown version
+        assert!(decode_challenge(&format!("{}!", code)).is_err()); // Not a base62 digit
+        assert!(decode_challenge("1").is_err());
+        assert!(decode_challenge(&code_for(7 << 16 | 4 << 8 | 1 << 4)).is_err()); // Reserved bit
+        assert!(decode_challenge(&code_for(7 << 16 | 4 << 8 | 3 << 1)).is_err()); // No fourth score rule
+        assert!(decode_challenge(&code_for(7 << 16 | 5 << 8)).is_err()); // 5x5 board
+    }
+
+    #[test]
+    fn keymap_bindings_override_defaults() {
+        let keys = build_keymap([("k", "up"), ("Space", "undo"), ("TAB", "quit")].into_iter()).unwrap();
+        assert_eq!(keys.get(&KeyCode::Char('k')), Some(&Action::Move(Direction::Up)));
+        assert_eq!(keys.get(&KeyCode::Char(' ')), Some(&Action::Undo)); // Was restart
+        assert_eq!(keys.get(&KeyCode::Tab), Some(&Action::Quit));
+        assert_eq!(keys.get(&KeyCode::Char('w')), Some(&Action::Move(Direction::Up))); // Untouched default
+
+        // The same binding twice is fine, two actions on one key isn't
+        assert!(build_keymap([("k", "up"), ("k", "up")].into_iter()).is_ok());
+        assert_eq!(
+            build_keymap([("k", "up"), ("k", "down")].into_iter()).unwrap_err(),
+            "key 'k' is bound to both Move(Up) and Move(Down)"
+        );
+        assert!(build_keymap([("pagedown", "up")].into_iter()).is_err());
+        assert!(build_keymap([("k", "jump")].into_iter()).is_err());
+    }
+
+    #[test]
+    fn scramble_keeps_every_tile() {
+        let mut game = empty_game();
+        game.grid = grid_of([[2, 4, 0, 0], [8, 0, 2, 0], [0, 0, 0, 16], [0; 4]]);
+        game.score = 40;
+        let sorted = |game: &Game| {
+            let mut tiles: Vec<(u32, usize)> = game.tiles().map(|(_, _, t)| (t.val, t.id)).collect();
+            tiles.sort_unstable();
+            tiles
+        };
+        let before = sorted(&game);
+        let grid = values_of(&game.grid);
+        // Five tiles can land where they started; a few shuffles won't all do that
+        let mut shuffled = false;
+        for _ in 0..5 {
+            game.scramble();
+            assert_eq!(sorted(&game), before);
+            shuffled |= values_of(&game.grid) != grid;
+        }
+        assert!(shuffled);
+        assert_eq!(game.score, 40);
+        assert!(!game.replayable);
+    }
+
+    #[test]
+    fn spawn_weights_shape_the_rolls() {
+        let mut game = Game::new(Some(3), SpawnRules { weights: vec![(2, 0.5), (8, 0.5)], ..SpawnRules::default() });
+        let rolls: Vec<u32> = (0..2000).map(|_| game.roll_value()).collect();
+        assert!(rolls.iter().all(|&v| v == 2 || v == 8));
+        let eights = rolls.iter().filter(|&&v| v == 8).count();
+        assert!((900..1100).contains(&eights), "{} eights in 2000", eights);
+
+        let parse = |entries: &[(&'static str, &'static str)]| parse_spawn_weights(entries.iter().copied());
+        assert_eq!(parse(&[("2", "0.9"), ("4", "0.1")]), Ok(Some(vec![(2, 0.9), (4, 0.1)])));
+        assert_eq!(parse(&[]), Ok(None));
+        assert_eq!(parse(&[("3", "1")]), Err("spawn value 3 is not a power of two".to_string()));
+        assert_eq!(parse(&[("1", "1")]), Err("spawn value 1 is not a power of two".to_string()));
+        assert_eq!(parse(&[("2", "0.5"), ("4", "0.3")]), Err("spawn weights add up to 0.8, not 1".to_string()));
+        assert!(parse(&[("2", "1.5")]).is_err());
+    }
+
+    #[test]
+    fn win_streak_survives_a_won_game_ending_but_not_a_loss() {
+        let mut stats = Stats::default();
+        stats.record_win();
+        stats.record_game_over(3000, true);
+        assert_eq!((stats.best, stats.win_streak), (3000, 1));
+
+        stats.record_win();
+        stats.record_game_over(2500, true);
+        assert_eq!((stats.best, stats.win_streak), (3000, 2));
+
+        stats.record_game_over(800, false);
+        assert_eq!((stats.best, stats.win_streak), (3000, 0));
+        stats.record_game_over(4000, false);
+        assert_eq!((stats.best, stats.win_streak), (4000, 0));
+    }
+
+    #[test]
+    fn import_reads_bare_and_object_boards() {
+        let rows = "[[2,0,0,0],[0,4,0,0],[0,0,8,0],[0,0,0,2048]]";
+        let bare = parse_import(rows).unwrap();
+        assert_eq!(bare.values, [[2, 0, 0, 0], [0, 4, 0, 0], [0, 0, 8, 0], [0, 0, 0, 2048]]);
+        assert_eq!(bare.score, None);
+
+        let object = parse_import(&format!("{{ \"score\": 120, \"board\": {} }}", rows)).unwrap();
+        assert_eq!(object.values, bare.values);
+        assert_eq!(object.score, Some(120));
+        assert_eq!(parse_import(&format!("{{\"grid\":{}}}", rows)).unwrap().values, bare.values);
+    }
+
+    #[test]
+    fn import_rejects_malformed_boards() {
+        let err = |text: &str| parse_import(text).unwrap_err();
+        assert_eq!(err("[[2,0,0,0],[0,0,0,0],[0,0,0,0]]"), "the board must be 4 rows of 4");
+        assert_eq!(err("[[2,0,0],[0,0,0,0],[0,0,0,0],[0,0,0,0]]"), "the board must be 4 rows of 4");
+        assert_eq!(err("[[2,0,0,3],[0,0,0,0],[0,0,0,0],[0,0,0,0]]"), "3 at row 0, column 3 isn't a tile value");
+        assert_eq!(err("[[1,0,0,0],[0,0,0,0],[0,0,0,0],[0,0,0,0]]"), "1 at row 0, column 0 isn't a tile value");
+        assert_eq!(err("[[[2,0,0,0]],[0,0,0,0],[0,0,0,0],[0,0,0,0]]"), "the board isn't an array of arrays of numbers");
+        assert_eq!(err("[[2,0,0,0],[0,0,0,0]"), "the board isn't an array of arrays of numbers");
+        assert_eq!(err("{\"score\": 4}"), "no \"board\" or \"grid\" array");
+    }
+
+    #[test]
+    fn no_color_beats_theme_and_force_color_beats_no_color() {
+        let depth = ColorDepth::Ansi256;
+        let original = Theme::Original { depth: ColorDepth::TrueColor };
+        assert_eq!(resolve_theme(Theme::Classic, false, false, depth), Theme::Classic);
+        assert_eq!(resolve_theme(original, false, false, depth), Theme::Original { depth });
+        assert_eq!(resolve_theme(Theme::Classic, true, false, depth), Theme::Mono);
+        assert_eq!(resolve_theme(original, true, false, depth), Theme::Mono);
+        assert_eq!(resolve_theme(Theme::Classic, true, true, depth), Theme::Classic);
+        assert_eq!(resolve_theme(original, true, true, depth), Theme::Original { depth });
+        assert_eq!(resolve_theme(Theme::Mono, false, true, depth), Theme::Mono); // Asked for by name
+    }
+
+    #[test]
+    fn ctrl_c_quits_even_though_c_exports() {
+        let settings = Settings::default();
+        let plain = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::NONE);
+        let ctrl = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(key_action(&plain, &settings), Some(Action::Export));
+        assert_eq!(key_action(&ctrl, &settings), Some(Action::Quit));
+    }
+
+    #[test]
+    fn a_new_move_forgets_what_undo_took_back() {
+        let mut game = empty_game();
+        game.grid = grid_of([[2, 2, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        game.enter_sandbox();
+        let start = values_of(&game.grid);
+
+        assert!(commit(&mut game, Direction::Left));
+        let after = values_of(&game.grid);
+        assert!(game.undo());
+        assert_eq!(values_of(&game.grid), start);
+        assert!(game.redo());
+        assert_eq!(values_of(&game.grid), after);
+        assert!(!game.redo());
+
+        assert!(game.undo());
+        assert_eq!(game.redo_stack.len(), 1);
+        assert!(commit(&mut game, Direction::Right));
+        assert!(game.redo_stack.is_empty());
+        assert!(!game.redo());
+    }
+
+    #[test]
+    fn frozen_tile_holds_until_thawed() {
+        let mut grid = grid_of([[0, 0, 0, 2], [0; 4], [0; 4], [0; 4]]);
+        grid[0][3].as_mut().unwrap().frozen = FREEZE_MOVES;
+        for _ in 0..FREEZE_MOVES {
+            assert!(!slide_and_merge(&grid, Direction::Left, MergeRule::Doubling).moved);
+            thaw_tiles(&mut grid);
+        }
+        let outcome = slide_and_merge(&grid, Direction::Left, MergeRule::Doubling);
+        assert!(outcome.moved);
+        assert_eq!(values_of(&outcome.grid)[0], [2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn difficulty_presets_and_flags_on_top() {
+        let four = |settings: &Settings| settings.spawn_weights.iter().find(|&&(v, _)| v == 4).map(|&(_, w)| w);
+        let easy = Difficulty::Easy.settings();
+        assert_eq!((four(&easy), easy.ramp), (Some(0.05), false));
+        assert_eq!(four(&Difficulty::Normal.settings()), four(&Settings::default()));
+        let hard = Difficulty::Hard.settings();
+        assert_eq!((four(&hard), hard.ramp, hard.garbage), (Some(0.2), true, 0));
+        let insane = Difficulty::Insane.settings();
+        assert_eq!((four(&insane), insane.ramp, insane.no_adjacent_spawn, insane.garbage), (Some(0.3), true, true, 10));
+
+        // Flags win over the preset, wherever they sit on the command line
+        let settings = args(&["--garbage", "3", "--difficulty", "insane"]).unwrap();
+        assert_eq!((settings.garbage, four(&settings), settings.no_adjacent_spawn), (3, Some(0.3), true));
+        assert!(args(&["--difficulty", "nightmare"]).is_err());
+    }
+
+    // A TestBackend whose next `failures` draws fail, counting the clears in between
+    struct Flaky {
+        inner: TestBackend,
+        failures: u32,
+        clears: u32,
+    }
+
+    impl ratatui::backend::Backend for Flaky {
+        fn draw<'a, I: Iterator<Item = (u16, u16, &'a ratatui::buffer::Cell)>>(&mut self, content: I) -> io::Result<()> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "link dropped"));
+            }
+            self.inner.draw(content)
+        }
+        fn hide_cursor(&mut self) -> io::Result<()> { self.inner.hide_cursor() }
+        fn show_cursor(&mut self) -> io::Result<()> { self.inner.show_cursor() }
+        fn get_cursor(&mut self) -> io::Result<(u16, u16)> { self.inner.get_cursor() }
+        fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> { self.inner.set_cursor(x, y) }
+        fn clear(&mut self) -> io::Result<()> {
+            self.clears += 1;
+            self.inner.clear()
+        }
+        fn size(&self) -> io::Result<Rect> { self.inner.size() }
+        fn window_size(&mut self) -> io::Result<ratatui::backend::WindowSize> { self.inner.window_size() }
+        fn flush(&mut self) -> io::Result<()> { self.inner.flush() }
+    }
+
+    #[test]
+    fn draw_frame_recovers_from_one_failed_write() {
+        let game = empty_game();
+        let (view, settings) = (View::new(), Settings::default());
+        let flaky = |failures| Terminal::new(Flaky { inner: TestBackend::new(80, 40), failures, clears: 0 }).unwrap();
+
+        let mut terminal = flaky(1);
+        draw_frame(&mut terminal, &game, &view, &settings).unwrap();
+        assert_eq!(terminal.backend().clears, 1);
+        assert!(text_at(terminal.backend().inner.buffer(), 0, 1, 80).contains("SCORE: 0"));
+
+        // Only the one retry: a second failure goes up to run()
+        let mut terminal = flaky(2);
+        assert!(draw_frame(&mut terminal, &game, &view, &settings).is_err());
+    }
+
+    #[test]
+    fn tile_size_steps_down_to_fit() {
+        let area = |width, height| Rect { x: 0, y: 0, width, height };
+        let preferred = (TILE_WIDTH, TILE_HEIGHT);
+        assert_eq!(fit_tile_size(area(80, 40), 4, preferred), (18, 7));
+        assert_eq!(fit_tile_size(area(72, 28), 4, preferred), (18, 7)); // Exactly fits
+        assert_eq!(fit_tile_size(area(60, 30), 4, preferred), (14, 7));
+        assert_eq!(fit_tile_size(area(40, 20), 4, preferred), (8, 3));
+        assert_eq!(fit_tile_size(area(30, 10), 4, preferred), (6, 3)); // Nothing fits: the smallest
+        // A custom size is tried first, and only smaller presets after it
+        assert_eq!(fit_tile_size(area(80, 40), 4, (10, 5)), (10, 5));
+        assert_eq!(fit_tile_size(area(35, 16), 4, (10, 5)), (8, 3));
+    }
+
+    #[test]
+    fn center_rect_puts_the_odd_cell_after() {
+        let outer = Rect { x: 2, y: 1, width: 10, height: 7 };
+        assert_eq!(center_rect(outer, 4, 3), Rect { x: 5, y: 3, width: 4, height: 3 }); // 3+3 and 2+2 spare
+        assert_eq!(center_rect(outer, 5, 4), Rect { x: 4, y: 2, width: 5, height: 4 }); // 2+3 and 1+2 spare
+        assert_eq!(center_rect(outer, 20, 20), outer); // Clamped to the outer rect
+    }
+
+    #[test]
+    fn abbreviate_rounds_down() {
+        assert_eq!(abbreviate(999), "999");
+        assert_eq!(abbreviate(1024), "1K");
+        assert_eq!(abbreviate(131072), "131K");
+        assert_eq!(abbreviate(1048576), "1M");
+    }
+
+    #[test]
+    fn best_move_breaks_ties_up_left_down_right() {
+        let settings = Settings::default();
+        // The same under every rotation and mirror, so all four moves score the same
+        let square = grid_of([[0; 4], [0, 2, 2, 0], [0, 2, 2, 0], [0; 4]]);
+        assert_eq!(best_move(&square, &settings), Some(Direction::Up));
+        // Symmetric about the diagonal, with up and left going nowhere
+        let corner = grid_of([[2, 4, 0, 0], [4, 0, 0, 0], [0; 4], [0; 4]]);
+        assert_eq!(best_move(&corner, &settings), Some(Direction::Down));
+        // Mirrored left to right, with up and down going nowhere
+        let rows = grid_of([[4, 0, 0, 4], [2, 0, 0, 2], [4, 0, 0, 4], [2, 0, 0, 2]]);
+        assert_eq!(best_move(&rows, &settings), Some(Direction::Left));
+    }
+
+    #[test]
+    fn snake_score_rewards_an_ordered_board() {
+        let snake = grid_of([[1024, 512, 256, 128], [8, 16, 32, 64], [4, 2, 0, 0], [0; 4]]);
+        assert_eq!(snake_score(&snake), 100);
+        // The same snake from another corner still counts
+        let mirrored = grid_of([[128, 256, 512, 1024], [64, 32, 16, 8], [0, 0, 2, 4], [0; 4]]);
+        assert_eq!(snake_score(&mirrored), 100);
+        let checkers = grid_of([[2, 1024, 2, 1024], [1024, 2, 1024, 2], [2, 1024, 2, 1024], [1024, 2, 1024, 2]]);
+        assert!(snake_score(&checkers) < 20, "{}", snake_score(&checkers));
+    }
+
+    #[test]
+    fn theoretical_score_counts_every_merge_from_twos() {
+        assert_eq!(theoretical_score(&grid_of([[8, 0, 0, 0], [0; 4], [0; 4], [0; 4]])), 16);
+        assert_eq!(theoretical_score(&grid_of([[2, 0, 0, 0], [0; 4], [0; 4], [0; 4]])), 0);
+        // 2 -> 0, 4 -> 4, 32 -> 128, 128 -> 768
+        assert_eq!(theoretical_score(&grid_of([[2, 4, 0, 0], [0, 32, 0, 0], [0; 4], [0, 0, 0, 128]])), 900);
+    }
+
+    #[test]
+    fn ends_game_counts_the_garbage_spawn() {
+        let mut game = empty_game();
+        let plain = Settings::default();
+        // A full board where left makes one merge: the spawn takes the only free cell
+        game.grid = grid_of([[2, 4, 8, 16], [16, 8, 4, 2], [2, 4, 8, 16], [32, 32, 64, 128]]);
+        assert!(ends_game(&game, &plain, Direction::Left));
+        assert!(!ends_game(&game, &plain, Direction::Up)); // Doesn't move at all
+
+        // Two merges leave two free cells, enough unless a garbage tile is due after this move
+        game.grid = grid_of([[2, 4, 8, 16], [16, 8, 4, 2], [2, 4, 8, 16], [32, 32, 64, 64]]);
+        assert!(!ends_game(&game, &plain, Direction::Left));
+        let garbage = Settings { garbage: 5, ..Settings::default() };
+        game.moves = 4;
+        assert!(ends_game(&game, &garbage, Direction::Left));
+        game.moves = 5;
+        assert!(!ends_game(&game, &garbage, Direction::Left));
+
+        game.moves = 4;
+        game.enter_sandbox();
+        assert!(!ends_game(&game, &garbage, Direction::Left)); // The sandbox never ends
+    }
+
+    #[test]
+    fn phase_times_fit_the_move_budget() {
+        let full = PhaseTimes { slide: SLIDE_FRAME, merge: MERGE_FRAME, flash: FLASH_FRAME, spawn: SPAWN_FRAME, delay: Duration::ZERO };
+        let spent = |t: PhaseTimes, slides: u32, flash: bool| {
+            t.slide * slides + t.merge + t.spawn + if flash { t.flash } else { Duration::ZERO }
+        };
+        assert_eq!(phase_times(3, true, true, true, Duration::ZERO, None), full);
+        // 3 x 50 + 50 + 120 + 50 = 370ms already fits 400ms
+        assert_eq!(phase_times(3, true, true, true, Duration::ZERO, Some(Duration::from_millis(400))), full);
+
+        for budget in [1, 37, 100, 250, 369] {
+            let budget = Duration::from_millis(budget);
+            for (slides, flash) in [(1, false), (3, true), (8, true)] {
+                let times = phase_times(slides as usize, true, flash, true, Duration::ZERO, Some(budget));
+                assert!(spent(times, slides, flash) <= budget, "{:?} over {:?}", times, budget);
+            }
+        }
+        // Proportions hold: the flash stays 2.4 slides long
+        let times = phase_times(3, true, true, true, Duration::ZERO, Some(Duration::from_millis(185)));
+        assert_eq!(times.slide, Duration::from_millis(25));
+        assert_eq!(times.flash, Duration::from_millis(60));
+    }
+
+    #[test]
+    fn ramp_shifts_weight_onto_bigger_spawns() {
+        let rules = SpawnRules { ramp: true, ..SpawnRules::default() };
+        let weight = |weights: &[(u32, f64)], value| weights.iter().find(|&&(v, _)| v == value).map_or(0.0, |&(_, w)| w);
+        let start = rules.weights_at(0);
+        assert_eq!((weight(&start, 2), weight(&start, 4), weight(&start, 8)), (0.9, 0.1, 0.0));
+        let later = rules.weights_at(100); // Level 2: 6% of the 2s become 4s
+        assert_ne!(later, start);
+        assert!((weight(&later, 2) - 0.84).abs() < 1e-9 && (weight(&later, 4) - 0.16).abs() < 1e-9, "{:?}", later);
+        // Without the ramp nothing changes
+        assert_eq!(SpawnRules::default().weights_at(100), DEFAULT_SPAWN_WEIGHTS.to_vec());
+    }
+
+    #[test]
+    fn random_start_fills_its_share_without_getting_stuck() {
+        for (fill, tiles) in [(0.01, 2), (0.25, 4), (0.5, 8), (1.0, 15)] {
+            for seed in 0..20 {
+                let game = Game::new(Some(seed), SpawnRules { start_fill: Some(fill), ..SpawnRules::default() });
+                assert_eq!(game.tile_count(), tiles, "fill {} seed {}", fill, seed);
+                assert!(game.tiles().all(|(_, _, t)| t.val == 2 || t.val == 4));
+                assert!(!is_stuck(&game.grid, &Settings::default()));
+            }
+        }
+    }
+
+    #[test]
+    fn achievements_unlock_once() {
+        let mut achievements = Achievements::default();
+        let mut game = empty_game();
+        game.grid = grid_of([[256, 0, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        assert!(achievements.check(&game).is_empty());
+
+        game.grid = grid_of([[512, 0, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        game.dir_merges = [40, 30, 20, 10];
+        assert_eq!(achievements.check(&game), vec![Achievement::First512, Achievement::HundredMerges]);
+        assert!(achievements.check(&game).is_empty()); // Already unlocked
+
+        // A win with an undo, well past the quick-win time, earns neither win achievement
+        game.won = true;
+        game.undos = 1;
+        game.paused_at = Some(game.started + QUICK_WIN + Duration::from_secs(1)); // Clock stopped at 3:01
+        assert!(achievements.check(&game).is_empty());
+
+        let mut fresh = Achievements::default();
+        let mut quick = empty_game();
+        quick.won = true;
+        assert_eq!(fresh.check(&quick), vec![Achievement::WinWithoutUndo, Achievement::QuickWin]);
+    }
+
+    #[test]
+    fn color_depth_flag_beats_config_beats_detection() {
+        let config = Config::parse("[settings]\ncolor_depth = 256\n").unwrap();
+        let from = |flags: &[&str], config: &Config| {
+            Settings::from_args(flags.iter().map(|f| f.to_string()).collect(), config).unwrap().color_depth()
+        };
+        assert_eq!(from(&[], &Config::default()), ColorDepth::detect());
+        assert_eq!(from(&[], &config), ColorDepth::Ansi256);
+        assert_eq!(from(&["--color-depth", "16"], &config), ColorDepth::Ansi16);
+        assert_eq!(from(&["--color-depth", "truecolor"], &Config::default()), ColorDepth::TrueColor);
+        assert!(args(&["--color-depth", "8"]).is_err());
+    }
+
+    #[test]
+    fn verify_entry_uses_only_the_entrys_rules() {
+        let played = Settings {
+            ramp: true,
+            random_start: Some(0.5),
+            spawn_weights: vec![(2, 0.5), (4, 0.5)],
+            ..Settings::default()
+        };
+        let mut game = Game::new(Some(21), played.spawn_rules());
+        for dir in [Direction::Left, Direction::Up, Direction::Right, Direction::Down].repeat(20) {
+            if replay_step(&mut game, dir, &played).is_some() {
+                game.move_log.push(dir);
+            }
+        }
+        let entry = GameSummary::from_json(&GameSummary::new(&game, &played).to_json()).unwrap();
+        assert!(entry.ramp);
+        assert_eq!(entry.random_start, Some(0.5));
+        assert_eq!(entry.spawn_weights, vec![(2, 0.5), (4, 0.5)]);
+        // The verifier runs on the defaults, yet the entry still replays under its own rules
+        assert!(verify_entry(&entry));
+    }
+
+    #[test]
+    fn launch_mode_needs_both_ttys_unless_piped() {
+        assert_eq!(launch_mode(false, true, true), Launch::Interactive);
+        assert_eq!(launch_mode(false, false, true), Launch::Refuse);
+        assert_eq!(launch_mode(false, true, false), Launch::Refuse);
+        assert_eq!(launch_mode(false, false, false), Launch::Refuse);
+        for (stdin_tty, stdout_tty) in [(true, true), (false, true), (true, false), (false, false)] {
+            assert_eq!(launch_mode(true, stdin_tty, stdout_tty), Launch::Pipe);
+        }
+    }
+
+    #[test]
+    fn keys_during_an_animation_queue_in_order_up_to_the_cap() {
+        let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+        let settings = Settings::default();
+        let events = Events::spawn(false);
+        let mut game = empty_game();
+        game.grid = grid_of([[0, 0, 0, 2], [0; 4], [0; 4], [0; 4]]);
+        let mut view = View::new();
+
+        // Typed before the slide starts, so every key is waiting while its frames play
+        for code in [KeyCode::Up, KeyCode::Right, KeyCode::Down, KeyCode::Left, KeyCode::Up, KeyCode::Down] {
+            events.press(code);
+        }
+        assert!(animate_move(&mut terminal, &events, &mut game, &mut view, &settings, Direction::Left).unwrap());
+
+        assert_eq!(MAX_QUEUED_MOVES, 4);
+        assert_eq!(
+            view.queued_moves.iter().copied().collect::<Vec<_>>(),
+            [Direction::Up, Direction::Right, Direction::Down, Direction::Left]
+        );
+    }
+
+    // Keeps every event as the line the event log would get, minus the clock
+    struct Capture(Rc<RefCell<Vec<String>>>);
+
+    impl EventSink for Capture {
+        fn emit(&mut self, event: &GameEvent) {
+            self.0.borrow_mut().push(event_json(event, 0));
+        }
+    }
+
+    // Left merges the top row's 2s, leaving a single gap, and the forced 2 there ends the game
+    fn one_move_from_game_over() -> Settings {
+        Settings {
+            import: Some(ImportedBoard {
+                values: [[2, 2, 8, 16], [16, 32, 64, 128], [4, 8, 16, 32], [32, 64, 128, 256]],
+                score: None,
+            }),
+            spawn_weights: vec![(2, 1.0)],
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn a_scripted_game_emits_its_events_as_json() {
+        let settings = one_move_from_game_over();
+        let lines = Rc::new(RefCell::new(Vec::new()));
+        let mut sinks: Vec<Box<dyn EventSink>> = vec![Box::new(Capture(Rc::clone(&lines)))];
+        run_pipe(&settings, io::Cursor::new("up\nleft\n"), io::sink(), &mut sinks).unwrap();
+
+        assert_eq!(
+            *lines.borrow(),
+            [
+                r#"{"ts":0,"event":"move","dir":"Left","score":4}"#,
+                r#"{"ts":0,"event":"merge","value":4}"#,
+                r#"{"ts":0,"event":"spawn","row":0,"col":3,"value":2}"#,
+                r#"{"ts":0,"event":"game_over","score":4,"moves":1}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn a_merge_runs_the_merge_command() {
+        let path = env::temp_dir().join(format!("rust2048-merge-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let mut sink = CommandEventSink::new(&format!("touch '{}'", path.display()));
+
+        sink.emit(&GameEvent::Move { dir: Direction::Left, score: 4 });
+        assert!(sink.running.is_none()); // Only merges run it
+
+        sink.emit(&GameEvent::Merge { value: 4 });
+        assert!(sink.running.take().unwrap().wait().unwrap().success());
+        assert!(path.exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_scripted_game_logs_every_kind_of_event() {
+        let path = env::temp_dir().join(format!("rust2048-log-{}.log", process::id()));
+        let _ = fs::remove_file(&path);
+        // The logger is process-wide, so this is the only test that may install one
+        init_logger(path.to_str().unwrap(), LevelFilter::Debug).unwrap();
+        run_pipe(&one_move_from_game_over(), io::Cursor::new("left\n"), io::sink(), &mut []).unwrap();
+        log::logger().flush();
+
+        let log = fs::read_to_string(&path).unwrap();
+        for record in [
+            "DEBUG Move { dir: Left, score: 4 }",
+            "DEBUG Merge { value: 4 }",
+            "DEBUG Spawn { row: 0, col: 3, value: 2 }",
+            "INFO  GameOver { score: 4, moves: 1 }",
+        ] {
+            assert!(log.lines().any(|line| line.ends_with(record)), "no '{}' in:\n{}", record, log);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn game_summary_survives_a_round_trip() {
+        let written = GameSummary {
+            finished: 1_760_000_000,
+            seed: 42,
+            score: 1234,
+            max_tile: 256,
+            moves: 87,
+            duration: Duration::from_millis(65_432),
+            won: true,
+            score_rule: ScoreRule::Log,
+            merge_rule: MergeRule::Fibonacci,
+            no_adjacent_spawn: true,
+            garbage: 3,
+            spawn_weights: vec![(2, 0.75), (4, 0.25)],
+            ramp: true,
+            random_start: Some(0.5),
+            log: None,
+            checksum: None,
+        }
+        .with_log(Some("LURD".to_string()));
+        let read = GameSummary::from_json(&written.to_json()).unwrap();
+
+        assert_eq!(
+            (read.finished, read.seed, read.score, read.max_tile, read.moves, read.duration, read.won),
+            (1_760_000_000, 42, 1234, 256, 87, Duration::from_millis(65_432), true)
+        );
+        assert_eq!(read.score_rule, ScoreRule::Log);
+        assert!(matches!(read.merge_rule, MergeRule::Fibonacci));
+        assert_eq!((read.no_adjacent_spawn, read.garbage), (true, 3));
+        assert_eq!(read.spawn_weights, [(2, 0.75), (4, 0.25)]);
+        assert_eq!((read.ramp, read.random_start), (true, Some(0.5)));
+        assert_eq!(read.log.as_deref(), Some("LURD"));
+        assert_eq!(read.checksum, written.checksum);
+        assert!(read.checksum.is_some());
+    }
+
+    #[test]
+    fn the_stored_seed_rebuilds_the_opening_board() {
+        for settings in [Settings::default(), Settings { random_start: Some(0.5), ..Settings::default() }] {
+            let game = Game::new(None, settings.spawn_rules());
+            let rebuilt = Game::new(Some(game.seed), settings.spawn_rules());
+            assert_eq!(values_of(&rebuilt.grid), values_of(&game.grid));
+        }
+    }
+
+    #[test]
+    fn a_forced_spawn_lands_once_then_spawning_resumes() {
+        let settings = Settings::default();
+        let mut game = empty_game();
+        game.grid = grid_of([[0, 0, 0, 2], [0; 4], [0; 4], [0; 4]]);
+        game.spawn_override = Some((3, 3, 64));
+
+        let first = replay_step(&mut game, Direction::Left, &settings).unwrap();
+        assert_eq!(first.spawns, [(3, 3, 64)]);
+        assert!(game.spawn_override.is_none());
+        assert!(!game.replayable);
+
+        let second = replay_step(&mut game, Direction::Up, &settings).unwrap();
+        let [(_, _, value)] = second.spawns[..] else { panic!("expected one spawn, got {:?}", second.spawns) };
+        assert!(value == 2 || value == 4);
+        assert_eq!(game.tiles().filter(|&(_, _, tile)| tile.val == 64).count(), 1);
+    }
+
+    #[test]
+    fn garbage_every_third_move_adds_a_second_tile() {
+        let settings = Settings { garbage: 3, ..Settings::default() };
+        let mut game = empty_game();
+        game.grid = grid_of([[0, 0, 0, 0], [0, 128, 0, 0], [0; 4], [0; 4]]);
+
+        // One spawn a move, plus the garbage 2 on the third; every merge takes a tile away
+        for (dir, spawned) in [(Direction::Left, 1), (Direction::Right, 1), (Direction::Up, 2)] {
+            let before = game.tile_count();
+            let step = replay_step(&mut game, dir, &settings).unwrap();
+            assert_eq!(step.spawns.len(), spawned);
+            assert_eq!(game.tile_count(), before - step.merged.len() + spawned);
+        }
+    }
+
+    #[test]
+    fn a_crowded_board_is_under_more_pressure() {
+        let settings = Settings::default();
+        // One gap and nothing to merge, so only the two moves toward the gap slide
+        let crowded = grid_of([[2, 4, 8, 16], [32, 64, 128, 256], [2, 4, 8, 16], [32, 64, 128, 0]]);
+        let roomy = grid_of([[2, 0, 0, 0], [0, 4, 0, 0], [0; 4], [0, 0, 0, 8]]);
+
+        assert_eq!(board_pressure(&roomy, &settings), 1.0 - (13.0 / 16.0 + 1.0) / 2.0);
+        assert_eq!(board_pressure(&crowded, &settings), 1.0 - (1.0 / 16.0 + 0.5) / 2.0);
+        assert!(board_pressure(&crowded, &settings) >= DANGER_PRESSURE);
+        assert!(board_pressure(&roomy, &settings) < DANGER_PRESSURE);
+    }
+
+    #[test]
+    fn a_milestone_fires_once_per_tier() {
+        let mut game = empty_game();
+        game.grid = grid_of([[128, 0, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        assert_eq!(game.note_milestone(), Some(128));
+        assert_eq!(game.note_milestone(), None);
+
+        game.grid = grid_of([[256, 0, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        assert_eq!(game.note_milestone(), Some(256));
+        assert_eq!(game.milestones, [128, 256]);
+    }
+
+    #[test]
+    fn block_from_is_the_first_value_drawn_in_blocks() {
+        let settings = Settings { block_from: 64, ..Settings::default() };
+        let block = |val: u32| uses_block_text(val, &val.to_string(), TILE_WIDTH, TILE_HEIGHT, &settings);
+        assert!(!block(32));
+        assert!(block(64));
+        assert!(block(128));
+        // Still plain when the tile is too small for blocks at all
+        assert!(!uses_block_text(128, "128", 8, 3, &settings));
+    }
+
+    #[test]
+    fn letters_count_doublings_and_stop_at_z() {
+        assert_eq!(value_to_letter(2), 'A');
+        assert_eq!(value_to_letter(4), 'B');
+        assert_eq!(value_to_letter(2048), 'K');
+        assert_eq!(value_to_letter(1 << 26), 'Z');
+        assert_eq!(value_to_letter(1 << 30), 'Z');
+    }
+
+    #[test]
+    fn exponent_labels_show_the_power_of_two() {
+        let settings = Settings { exponent: true, ..Settings::default() };
+        assert_eq!(tile_label(2, &settings), "1");
+        assert_eq!(tile_label(1024, &settings), "10");
+        assert_eq!(tile_label(131072, &settings), "17");
+    }
+
+    #[test]
+    fn minimap_draws_a_cell_per_square() {
+        let mut game = empty_game();
+        game.grid = grid_of([[2, 0, 0, 0], [0, 2048, 0, 0], [0; 4], [0, 0, 0, 8]]);
+        let text: Vec<String> = render_minimap(&game, Theme::Mono).iter().map(|line| line.to_string()).collect();
+        assert_eq!(text, ["■···", "·■··", "····", "···■"]);
+
+        // Colour themes tint each tile by its tier
+        let two = minimap_cell(Some(Tile { val: 2, id: 0, frozen: 0 }), Theme::Classic);
+        assert_eq!(two.style.fg, get_color_style(2, Theme::Classic).bg);
+        assert_ne!(two.style.fg, minimap_cell(Some(Tile { val: 8, id: 1, frozen: 0 }), Theme::Classic).style.fg);
+    }
+
+    #[test]
+    fn optimal_percent_rounds_down_and_survives_no_checks() {
+        assert_eq!(optimal_percent(3, 4), 75);
+        assert_eq!(optimal_percent(2, 3), 66);
+        assert_eq!(optimal_percent(5, 5), 100);
+        assert_eq!(optimal_percent(0, 0), 0);
+    }
+
+    #[test]
+    fn no_adjacent_spawn_takes_the_safe_cell() {
+        let rules = Settings { no_adjacent_spawn: true, spawn_weights: vec![(2, 1.0)], ..Settings::default() }.spawn_rules();
+        // Three gaps, and only (1, 2) has no 2 beside it
+        let grid = grid_of([[0, 2, 8, 16], [32, 64, 0, 128], [4, 8, 16, 2], [16, 32, 2, 0]]);
+        for seed in 0..20 {
+            let mut game = Game::new(Some(seed), rules.clone());
+            game.grid = grid;
+            assert_eq!(game.spawn_tile(), Some((1, 2, 2)), "seed {}", seed);
+        }
+
+        // With nowhere safe it still spawns rather than skipping the tile
+        let mut game = Game::new(Some(0), rules);
+        game.grid = grid_of([[0, 2, 8, 16], [32, 64, 2, 128], [4, 8, 16, 2], [16, 32, 2, 0]]);
+        assert!(matches!(game.spawn_tile(), Some((0, 0, 2) | (3, 3, 2))));
+    }
+
+    #[test]
+    fn progress_counts_doublings_toward_the_target() {
+        assert_eq!(progress_fraction(0, 2048), 0.0);
+        assert_eq!(progress_fraction(2, 2048), 1.0 / 11.0);
+        assert_eq!(progress_fraction(1024, 2048), 10.0 / 11.0);
+        assert_eq!(progress_fraction(2048, 2048), 1.0);
+        assert_eq!(progress_fraction(8192, 2048), 1.0); // Capped past the target
+        assert_eq!(progress_fraction(64, 0), 0.0);
+    }
+
+    #[test]
+    fn board_text_lists_every_cell_row_major() {
+        let grid = grid_of([[2, 0, 0, 4], [0; 4], [0, 1024, 0, 0], [0, 0, 0, 8]]);
+        assert_eq!(board_text(&grid), "2 0 0 4 0 0 0 0 0 1024 0 0 0 0 0 8");
+    }
+
+    #[test]
+    fn tiles_walks_the_occupied_cells_row_major() {
+        let mut game = empty_game();
+        game.grid = grid_of([[0, 2, 0, 0], [4, 0, 0, 8], [0; 4], [0, 0, 16, 0]]);
+        let tiles: Vec<(usize, usize, u32)> = game.tiles().map(|(r, c, tile)| (r, c, tile.val)).collect();
+        assert_eq!(tiles, [(0, 1, 2), (1, 0, 4), (1, 3, 8), (3, 2, 16)]);
+    }
+
+    #[test]
+    fn original_theme_keeps_the_web_palette() {
+        let theme = Theme::Original { depth: ColorDepth::TrueColor };
+        let colours = |val: u32| {
+            let style = get_color_style(val, theme);
+            (style.fg, style.bg)
+        };
+        assert_eq!(colours(2), (Some(Color::Rgb(119, 110, 101)), Some(Color::Rgb(238, 228, 218))));
+        assert_eq!(colours(8), (Some(Color::Rgb(249, 246, 242)), Some(Color::Rgb(242, 177, 121))));
+        assert_eq!(colours(2048), (Some(Color::Rgb(249, 246, 242)), Some(Color::Rgb(237, 194, 46))));
+        assert_eq!(colours(4096), (Some(Color::Rgb(249, 246, 242)), Some(Color::Rgb(60, 58, 50))));
+    }
+
+    #[test]
+    fn rgb_falls_back_to_the_nearest_palette_colour() {
+        assert_eq!(nearest_ansi16((250, 0, 0)), Color::LightRed);
+        assert_eq!(nearest_ansi16((200, 10, 0)), Color::Red);
+        assert_eq!(nearest_ansi16((100, 100, 240)), Color::LightBlue);
+        assert_eq!(nearest_ansi16((10, 10, 10)), Color::Black);
+
+        assert_eq!(nearest_ansi256((255, 0, 0)), Color::Indexed(196));
+        assert_eq!(nearest_ansi256((0, 0, 0)), Color::Indexed(16));
+        assert_eq!(nearest_ansi256((237, 194, 46)), Color::Indexed(214));
+        // Mid greys sit between cube levels, so the grey ramp wins
+        assert_eq!(nearest_ansi256((128, 128, 128)), Color::Indexed(244));
+    }
+
+    #[test]
+    fn moving_the_corner_max_out_is_flagged() {
+        let rule = MergeRule::Doubling;
+        let grid = grid_of([[256, 4, 0, 0], [2, 0, 0, 0], [0; 4], [0; 4]]);
+        assert!(displaces_corner_max(&grid, Direction::Right, rule));
+        assert!(displaces_corner_max(&grid, Direction::Down, rule));
+        assert!(!displaces_corner_max(&grid, Direction::Left, rule));
+
+        // Merging into the corner keeps a tile at least as big there
+        let pair = grid_of([[256, 256, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        assert!(!displaces_corner_max(&pair, Direction::Left, rule));
+        // And a max that isn't in a corner has nothing to lose
+        let centre = grid_of([[0; 4], [0, 256, 0, 0], [0; 4], [0; 4]]);
+        assert!(!displaces_corner_max(&centre, Direction::Right, rule));
+    }
+
+    #[test]
+    fn idle_timeout_trips_at_its_limit() {
+        let last_input = Instant::now();
+        let limit = Some(Duration::from_secs(60));
+        assert!(!idle_exceeded(last_input, last_input + Duration::from_secs(59), limit));
+        assert!(idle_exceeded(last_input, last_input + Duration::from_secs(60), limit));
+        assert!(!idle_exceeded(last_input, last_input + Duration::from_secs(3600), None));
+    }
+
+    #[test]
+    fn checker_shades_every_other_cell_except_in_mono() {
+        let plain = empty_cell_style(Theme::Classic);
+        assert_eq!(checker_style(0, 0, Theme::Classic), plain);
+        assert_eq!(checker_style(0, 1, Theme::Classic), plain.bg(Color::Indexed(235)));
+        assert_eq!(checker_style(1, 0, Theme::Classic), plain.bg(Color::Indexed(235)));
+        assert_eq!(checker_style(1, 1, Theme::Classic), plain);
+        assert_eq!(checker_style(0, 1, Theme::Mono), empty_cell_style(Theme::Mono));
+    }
+
+    #[test]
+    fn watch_file_moves_are_read_then_cleared() {
+        assert_eq!(watch_char('w'), Some(Direction::Up));
+        assert_eq!(watch_char('J'), Some(Direction::Down));
+        assert_eq!(watch_char('a'), Some(Direction::Left));
+        assert_eq!(watch_char('l'), Some(Direction::Right));
+        assert_eq!(watch_char('\n'), None);
+
+        let path = env::temp_dir().join(format!("rust2048-watch-{}", process::id()));
+        let path = path.to_str().unwrap();
+        assert!(consume_watch_file(path).is_empty()); // Not there yet
+        fs::write(path, "dx w\nH").unwrap();
+        assert_eq!(consume_watch_file(path), [Direction::Right, Direction::Up, Direction::Left]);
+        assert_eq!(fs::read_to_string(path).unwrap(), "");
+        assert!(consume_watch_file(path).is_empty());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn direction_counters_track_moves_and_their_merges() {
+        let mut game = empty_game();
+        game.grid = grid_of([[0, 0, 2, 2], [0, 0, 4, 4], [0; 4], [0; 4]]);
+
+        assert!(!commit(&mut game, Direction::Up)); // Nothing slides, so nothing counts
+        assert!(commit(&mut game, Direction::Left));
+        let left = Direction::Left.index();
+        assert_eq!((game.dir_moves[left], game.dir_merges[left]), (1, 2));
+        assert_eq!(game.dir_moves.iter().sum::<u32>(), 1);
+        assert_eq!(game.dir_merges.iter().sum::<u32>(), 2);
+    }
+
+    #[test]
+    fn the_cursor_stops_at_the_edges() {
+        assert_eq!(move_cursor((1, 2), Direction::Up), (0, 2));
+        assert_eq!(move_cursor((1, 2), Direction::Right), (1, 3));
+        assert_eq!(move_cursor((0, 0), Direction::Up), (0, 0));
+        assert_eq!(move_cursor((0, 0), Direction::Left), (0, 0));
+        assert_eq!(move_cursor((3, 3), Direction::Down), (3, 3));
+        assert_eq!(move_cursor((3, 3), Direction::Right), (3, 3));
+    }
+
+    #[test]
+    fn histogram_counts_each_value_smallest_first() {
+        let grid = grid_of([[8, 2, 0, 2], [0, 64, 2, 0], [8, 0, 0, 0], [0; 4]]);
+        assert_eq!(tile_histogram(&grid), [(2, 3), (8, 2), (64, 1)]);
+        assert!(tile_histogram(&[[None; 4]; 4]).is_empty());
+    }
+
+    #[test]
+    fn spawn_delay_is_read_and_held_before_the_spawn() {
+        let settings = args(&["--spawn-delay", "150", "--move-budget-ms", "60"]).unwrap();
+        assert_eq!(settings.spawn_delay, Duration::from_millis(150));
+        // The budget squeezes the frames but leaves the delay alone
+        let times = phase_times(3, true, false, true, settings.spawn_delay, settings.move_budget);
+        assert_eq!(times.delay, Duration::from_millis(150));
+        assert!(times.slide < SLIDE_FRAME);
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+        let mut game = empty_game();
+        game.grid = grid_of([[0, 0, 0, 2], [0; 4], [0; 4], [0; 4]]);
+        let started = Instant::now();
+        assert!(animate_move(&mut terminal, &Events::headless(), &mut game, &mut View::new(), &settings, Direction::Left).unwrap());
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn debug_anim_records_every_frame_of_a_move() {
+        let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+        let settings = Settings { debug_anim: true, anim_quality: AnimQuality::High, ..Settings::default() };
+        let events = Events::headless();
+        let mut game = empty_game();
+
+        // Three slide steps, then the spawn
+        game.grid = grid_of([[0, 0, 0, 2], [0; 4], [0; 4], [0; 4]]);
+        assert!(animate_move(&mut terminal, &events, &mut game, &mut View::new(), &settings, Direction::Left).unwrap());
+        assert_eq!(game.anim_frames.len(), 4);
+        assert_eq!(values_of(&game.anim_frames[0])[0], [0, 0, 2, 0]);
+        assert_eq!(values_of(&game.anim_frames[2])[0], [2, 0, 0, 0]);
+        assert_eq!(game.anim_frames[3], game.grid);
+
+        // No slide, one merge, then the spawn; the last move's frames are gone
+        game.grid = grid_of([[2, 2, 0, 0], [0; 4], [0; 4], [0; 4]]);
+        assert!(animate_move(&mut terminal, &events, &mut game, &mut View::new(), &settings, Direction::Left).unwrap());
+        assert_eq!(game.anim_frames.len(), 2);
+        assert_eq!(values_of(&game.anim_frames[0])[0], [4, 0, 0, 0]);
+    }
+
+    #[test]
+    fn startup_paints_just_the_opening_board() {
+        let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+        let settings = Settings { seed: Some(7), splash: false, ..Settings::default() };
+        let events = Events::headless();
+        events.press(KeyCode::Char('q'));
+        run(&mut terminal, &events, &settings, &mut []).unwrap();
+
+        let opening = Game::new(Some(7), settings.spawn_rules());
+        assert_eq!(*terminal.backend().buffer(), render(&opening, &settings, 80, 40));
+    }
+
+    #[test]
+    fn a_move_with_no_animation_takes_no_time() {
+        let settings = args(&["--no-spawn-anim"]).unwrap();
+        // No slide, merge or flash, and --no-spawn-anim drops the spawn frame: nothing to wait for
+        let times = phase_times(0, false, false, settings.spawn_anim, settings.spawn_delay, settings.move_budget);
+        let zero = Duration::ZERO;
+        assert_eq!(times, PhaseTimes { slide: zero, merge: zero, flash: zero, spawn: zero, delay: zero });
+
+        // Sliding still takes its frames, but the spawn adds nothing after them
+        let times = phase_times(2, false, false, settings.spawn_anim, settings.spawn_delay, settings.move_budget);
+        assert_eq!((times.slide, times.spawn), (SLIDE_FRAME, zero));
+    }
 }